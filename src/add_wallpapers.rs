@@ -1,59 +1,161 @@
-use std::{io::Write, path::PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use itertools::Itertools;
+use rayon::prelude::*;
+use serde::Serialize;
 use wallfacer::{
-    PathBufVecExt, cli::AddWallpaperArgs, config::Config, pipeline::WallpaperPipeline,
+    PathBufVecExt,
+    cli::AddWallpaperArgs,
+    config::Config,
+    pipeline::{AddOutcome, AddPlan, WallpaperPipeline},
 };
 
-pub fn main(config_path: Option<PathBuf>, args: &AddWallpaperArgs) {
-    let cfg = Config::new(config_path).expect("failed to load config");
-    let mut all_files = args.inputs.filter_wallpapers();
-    all_files.numeric_sort();
+#[derive(Serialize)]
+struct PlannedImage<'a> {
+    path: &'a Path,
+    #[serde(flatten)]
+    plan: Option<AddPlan>,
+    /// the image would fail to be added
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// checks that the image meets the minimum size requirement
+fn validate_image(img: &Path, cfg: &Config) -> Result<(), String> {
+    let (width, height) =
+        image::image_dimensions(img).map_err(|e| format!("could not get image dimensions: {e}"))?;
+    let (min_width, min_height) = cfg.min_dimensions(width, height);
+
+    if width * 4 < min_width || height * 4 < min_height {
+        return Err("too small".into());
+    }
 
-    // check that all the files meet the minimum size requirement
-    let invalid = all_files
+    Ok(())
+}
+
+/// prints what would be done to each image, without modifying anything
+fn dry_run(cfg: &Config, all_files: &[PathBuf], args: &AddWallpaperArgs) {
+    let pipeline = WallpaperPipeline::dry_run(cfg, args.format.clone(), args.output.clone())
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+
+    let planned = all_files
         .iter()
-        .filter_map(|img| {
-            let (width, height) = image::image_dimensions(img)
-                .unwrap_or_else(|_| panic!("could not get image dimensions for {}", img.display()));
-
-            if height > width {
-                return Some(format!("{:?} is not landscape!", img.display()));
-            }
-
-            if width * 4 < cfg.min_width || height * 4 < cfg.min_height {
-                return Some(format!("{:?} is too small!", img.display()));
-            }
-            None
+        .map(|img| {
+            let plan = validate_image(img, cfg)
+                .and_then(|()| pipeline.plan(img, args.force).map_err(|e| e.to_string()));
+            (img, plan)
         })
         .collect_vec();
 
-    for msg in &invalid {
-        eprintln!("{msg}");
+    if args.json {
+        let planned = planned
+            .into_iter()
+            .map(|(img, plan)| PlannedImage {
+                path: img,
+                plan: plan.as_ref().ok().copied(),
+                error: plan.err(),
+            })
+            .collect_vec();
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&planned).expect("could not serialize plan")
+        );
+        return;
+    }
+
+    for (img, plan) in planned {
+        match plan {
+            Ok(plan) => println!("{}: {plan}", img.display()),
+            Err(e) => println!("{}: Fail: {e}", img.display()),
+        }
     }
-    if !invalid.is_empty() {
-        std::process::exit(1);
+}
+
+pub fn main(config_path: Option<PathBuf>, args: &AddWallpaperArgs) {
+    let cfg = Config::new(config_path).expect("failed to load config");
+    let mut all_files = args.inputs.filter_wallpapers();
+    all_files.numeric_sort();
+
+    if args.dry_run {
+        dry_run(&cfg, &all_files, args);
+        return;
     }
 
-    let mut pipeline = WallpaperPipeline::new(&cfg, args.format.clone(), args.output.clone());
+    let pipeline = WallpaperPipeline::new(&cfg, args.format.clone(), args.output.clone())
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+
+    rayon::ThreadPoolBuilder::new()
+        // 0 uses the number of cpus
+        .num_threads(args.jobs.unwrap_or(0))
+        .build_global()
+        .expect("could not create thread pool");
+
     let img_count = all_files.len();
-    for (idx, img) in all_files.iter().enumerate() {
-        let start_time = std::time::Instant::now();
-        let status_line = format!(
-            "\r[{:0>width$}/{img_count}] Processing: {img}\t",
-            idx + 1,
-            width = img_count.to_string().len(),
-            img = img.display()
-        );
-        std::io::stdout().flush().expect("could not flush stdout");
-        pipeline.add_image(img, args.force, &status_line);
-        // need the tabs at the end to overwrite the previous line cleanly
-        println!(
-            "{status_line} ({:.3}s){}",
-            start_time.elapsed().as_secs_f64(),
-            " ".repeat(15),
+    let width = img_count.to_string().len();
+    let completed = AtomicUsize::new(0);
+
+    let results: Vec<_> = all_files
+        .par_iter()
+        .map(|img| {
+            let start_time = std::time::Instant::now();
+            let result = validate_image(img, &cfg).and_then(|()| {
+                pipeline
+                    .add_image(img, args.force)
+                    .map_err(|e| e.to_string())
+            });
+
+            let idx = completed.fetch_add(1, Ordering::Relaxed) + 1;
+            let status = match &result {
+                Ok(AddOutcome::Processed) => {
+                    format!("Processed ({:.3}s)", start_time.elapsed().as_secs_f64())
+                }
+                Ok(AddOutcome::Skipped) => "Skipped".to_string(),
+                Err(e) => format!("Failed: {e}"),
+            };
+            println!("[{idx:0>width$}/{img_count}] {}: {status}", img.display());
+
+            (img, result)
+        })
+        .collect();
+
+    let count = |outcome| {
+        results
+            .iter()
+            .filter(|(_, result)| result.as_ref().is_ok_and(|o| *o == outcome))
+            .count()
+    };
+    let failed: Vec<_> = results
+        .iter()
+        .filter_map(|(img, result)| result.as_ref().err().map(|e| (img, e)))
+        .collect();
+
+    println!(
+        "\n{} processed, {} skipped, {} failed",
+        count(AddOutcome::Processed),
+        count(AddOutcome::Skipped),
+        failed.len()
+    );
+
+    if !failed.is_empty() {
+        eprintln!(
+            "\n{} / {img_count} images could not be processed:",
+            failed.len()
         );
+        for (img, e) in &failed {
+            eprintln!("{}: {e}", img.display());
+        }
+        eprintln!("\nRun the same command again to retry the failed images");
     }
 
-    pipeline.preview();
+    pipeline.preview(!failed.is_empty());
 }
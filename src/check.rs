@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use wallfacer::{
+    PathBufVecExt, cli::CheckArgs, config::Config, report_failures, wallpapers::WallInfo,
+};
+
+/// prints the crops that are smaller than their monitor, returns if any were found
+fn check_undersized(info: &WallInfo, cfg: &Config) -> bool {
+    let cropper = info.default_cropper(&cfg.crop);
+    let undersized = cfg
+        .resolutions
+        .iter()
+        .filter_map(|res| {
+            let geom = info.get_geometry(&res.resolution, &cropper);
+
+            res.upscale_needed(&geom).map(|scale| {
+                format!(
+                    "    {} ({}x{}): crop {}x{} needs {scale:.2}x upscale",
+                    res.name, res.width, res.height, geom.w, geom.h
+                )
+            })
+        })
+        .collect_vec();
+
+    if undersized.is_empty() {
+        return false;
+    }
+
+    println!("{}", info.path.display());
+    for msg in undersized {
+        println!("{msg}");
+    }
+
+    true
+}
+
+pub fn main(config_path: Option<PathBuf>, args: &CheckArgs) {
+    let cfg = Config::new(config_path).expect("failed to load config");
+    let mut all_files = args.paths.filter_wallpapers();
+    all_files.numeric_sort();
+
+    // run all checks if none are specified
+    let check_all = !args.undersized;
+
+    let mut undersized_count = 0;
+    let mut failed = Vec::new();
+    for path in &all_files {
+        let info = match WallInfo::new_from_file(path, &cfg.metadata) {
+            Ok(info) => info,
+            Err(e) => {
+                failed.push((path, e));
+                continue;
+            }
+        };
+
+        if (check_all || args.undersized) && check_undersized(&info, &cfg) {
+            undersized_count += 1;
+        }
+    }
+
+    println!(
+        "\n{undersized_count} / {} wallpapers have undersized crops",
+        all_files.len()
+    );
+
+    report_failures("checked", &failed, all_files.len());
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+}
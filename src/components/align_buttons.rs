@@ -5,9 +5,9 @@ use dioxus_free_icons::icons::md_editor_icons::{
     MdFormatAlignCenter, MdFormatAlignLeft, MdFormatAlignRight, MdVerticalAlignBottom,
     MdVerticalAlignCenter, MdVerticalAlignTop,
 };
-use wallfacer::cropper::{Cropper, Direction};
+use wallfacer::cropper::Direction;
 
-use crate::components::button::PreviewableButton;
+use crate::components::button::{Button, PreviewableButton};
 use crate::state::Wall;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -32,7 +32,7 @@ fn AlignButton(
     let h = wall().current.height;
     let geom = match align_type {
         AlignType::Source => wall().get_current_geometry(),
-        AlignType::Default => wall().current.cropper().crop(&wall().ratio),
+        AlignType::Default => wall().get_default_geometry(),
         AlignType::Start => wall().get_current_geometry().align_start(w, h),
         AlignType::Center => wall().get_current_geometry().align_center(w, h),
         AlignType::End => wall().get_current_geometry().align_end(w, h),
@@ -48,15 +48,16 @@ fn AlignButton(
                 wall.with_mut(|wall| {
                     // holding shift performs for align for all crops in the same direction
                     if evt.modifiers().shift() {
+                        wall.checkpoint();
+                        let info = wall.current.clone();
+                        let cropper = wall.cropper();
+                        let source_cropper = wall.source_cropper();
                         for (ratio, geom) in &mut wall.current.geometries {
                             // same direction
-                            if geom.h == current_geom.h {
+                            if info.direction(geom) == info.direction(&current_geom) {
                                 *geom = match align_type {
-                                    AlignType::Source => wall.source.get_geometry(ratio),
-                                    AlignType::Default => {
-                                        let cropper = Cropper::new(&wall.current.faces, w, h);
-                                        cropper.crop(ratio)
-                                    },
+                                    AlignType::Source => wall.source.get_geometry(ratio, &source_cropper),
+                                    AlignType::Default => cropper.crop(ratio),
                                     AlignType::Start => geom.align_start(w, h),
                                     AlignType::Center => geom.align_center(w, h),
                                     AlignType::End => geom.align_end(w, h),
@@ -77,7 +78,8 @@ fn AlignButton(
 pub fn AlignButtons(wall: Signal<Wall>, class: Option<String>) -> Element {
     let Wall { current: info, .. } = wall();
     let geom = wall().get_current_geometry();
-    let dir = info.direction(&geom);
+    // zoomed crops are aligned along both axes, but use the horizontal icons
+    let dir = info.direction(&geom).unwrap_or(Direction::X);
 
     rsx! {
             span {
@@ -90,10 +92,18 @@ pub fn AlignButtons(wall: Signal<Wall>, class: Option<String>) -> Element {
                 }
                 AlignButton {
                     wall,
-                    class: "text-sm rounded-r-md",
+                    class: "text-sm -ml-px",
                     align_type: AlignType::Default,
                     "Default"
                 }
+                Button {
+                    class: "text-sm rounded-r-md -ml-px",
+                    title: "Propagate this crop to all ratios",
+                    onclick: move |_| {
+                        wall.with_mut(Wall::propagate_current_geometry);
+                    },
+                    "Propagate"
+                }
             }
 
             span {
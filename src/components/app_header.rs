@@ -2,40 +2,25 @@
 use dioxus::prelude::*;
 use dioxus_free_icons::Icon;
 use dioxus_free_icons::icons::{
-    md_image_icons::MdFaceRetouchingNatural,
+    md_image_icons::{MdEdit, MdFaceRetouchingNatural},
     md_navigation_icons::{MdChevronLeft, MdChevronRight},
 };
 
 use crate::{
-    components::{save_button::SaveButton, use_ui, wallpaper_button::WallpaperButton},
-    state::{UiMode, Wall, Wallpapers},
+    components::{
+        save_button::SaveButton, unsaved_prompt::navigate, use_ui,
+        wallpaper_button::WallpaperButton,
+    },
+    state::{Navigation, Wall, Wallpapers},
 };
 use wallfacer::{config::Config, filename};
 
-pub fn prev_image(wallpapers: &mut Signal<Wallpapers>) {
-    let mut ui = use_ui();
-
-    wallpapers.with_mut(|wallpapers| {
-        wallpapers.prev_wall();
-    });
-    ui.with_mut(|ui| {
-        if ui.mode == UiMode::FileList {
-            ui.mode = UiMode::Editor;
-        }
-    });
+pub fn prev_image(wall: &mut Signal<Wall>, wallpapers: &mut Signal<Wallpapers>) {
+    navigate(Navigation::Prev, wall, wallpapers);
 }
 
-pub fn next_image(wallpapers: &mut Signal<Wallpapers>) {
-    let mut ui = use_ui();
-
-    wallpapers.with_mut(|wallpapers| {
-        wallpapers.next_wall();
-    });
-    ui.with_mut(|ui| {
-        if ui.mode == UiMode::FileList {
-            ui.mode = UiMode::Editor;
-        }
-    });
+pub fn next_image(wall: &mut Signal<Wall>, wallpapers: &mut Signal<Wallpapers>) {
+    navigate(Navigation::Next, wall, wallpapers);
 }
 
 #[component]
@@ -86,13 +71,21 @@ pub fn AppHeader(wall: Signal<Wall>, wallpapers: Signal<Wallpapers>) -> Element
                     class: if !supports_adding { "ml-2" },
                     "{wallpapers().index + 1} / {wallpapers().files.len()}"
                 }
+
+                if wall().is_dirty() {
+                    span {
+                        class: "ml-3 rounded-md bg-ctp-peach px-2 py-1 text-xs font-semibold text-ctp-base",
+                        title: "Crops have unsaved changes",
+                        "modified"
+                    }
+                }
             }
 
             // center
             div { class: "flex flex-1 gap-x-3 justify-self-center",
                 a { class: pagination_cls,
                     onclick: move |_| {
-                        prev_image(&mut wallpapers);
+                        prev_image(&mut wall, &mut wallpapers);
                     },
                     Icon { fill: "white", icon:  MdChevronLeft, width: 16, height: 16 }
                 }
@@ -102,11 +95,11 @@ pub fn AppHeader(wall: Signal<Wall>, wallpapers: Signal<Wallpapers>) -> Element
                             ui.toggle_filelist();
                         });
                     },
-                    {filename(wallpapers().current().current.path)}
+                    {filename(&wall().current.path)}
                 }
                 a { class: pagination_cls,
                     onclick: move |_| {
-                        next_image(&mut wallpapers);
+                        next_image(&mut wall, &mut wallpapers);
                     },
                     Icon { fill: "white", icon:  MdChevronRight, width: 16, height: 16 }
                 }
@@ -134,6 +127,22 @@ pub fn AppHeader(wall: Signal<Wall>, wallpapers: Signal<Wallpapers>) -> Element
                     Icon { fill: "white", icon:  MdFaceRetouchingNatural }
                 }
 
+                a {
+                    class: "rounded-md px-3 py-2 text-sm font-semibold text-white shadow-sm focus-visible:outline-2 focus-visible:outline-offset-2 cursor-pointer",
+                    class: if ui().edit_faces {
+                        "bg-indigo-600 hover:bg-indigo-500"
+                    } else {
+                        "bg-ctp-surface1 hover:bg-ctp-crust"
+                    },
+                    title: "Edit faces",
+                    onclick: move |_| {
+                        ui.with_mut(|ui| {
+                            ui.edit_faces = !ui.edit_faces;
+                        });
+                    },
+                    Icon { fill: "white", icon:  MdEdit }
+                }
+
                 SaveButton { wall, wallpapers }
             }
         }
@@ -0,0 +1,39 @@
+#![allow(non_snake_case)]
+use dioxus::prelude::*;
+
+use crate::components::use_ui;
+
+#[component]
+pub fn BrokenBanner(broken: Vec<String>) -> Element {
+    let mut ui = use_ui();
+
+    rsx! {
+        // fixed so the banner doesn't take up a row in the main grid
+        div {
+            class: "fixed bottom-4 right-4 z-20 max-w-lg rounded-md bg-ctp-red p-4 text-sm text-ctp-base shadow-lg",
+            div {
+                class: "flex justify-between gap-x-4",
+                p {
+                    class: "font-semibold",
+                    "Skipped {broken.len()} wallpapers with unreadable metadata"
+                }
+                button {
+                    r#type: "button",
+                    class: "font-semibold cursor-pointer",
+                    onclick: move |_| {
+                        ui.with_mut(|ui| {
+                            ui.show_broken = false;
+                        });
+                    },
+                    "✕"
+                }
+            }
+            ul {
+                class: "mt-2 max-h-48 overflow-y-auto list-disc pl-5",
+                for msg in broken {
+                    li { {msg} }
+                }
+            }
+        }
+    }
+}
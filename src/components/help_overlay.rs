@@ -0,0 +1,59 @@
+#![allow(non_snake_case)]
+use dioxus::prelude::*;
+use itertools::Itertools;
+use wallfacer::keybindings::{Action, Keybindings};
+
+use crate::components::use_ui;
+
+#[component]
+pub fn HelpOverlay(keybindings: Keybindings) -> Element {
+    let mut ui = use_ui();
+
+    let mut close = move || {
+        ui.with_mut(|ui| {
+            ui.show_help = false;
+        });
+    };
+
+    rsx! {
+        div {
+            class: "fixed inset-0 z-30 flex items-center justify-center bg-ctp-crust/70",
+            onclick: move |_| close(),
+            div {
+                class: "max-h-[80vh] max-w-lg overflow-y-auto rounded-md bg-ctp-surface0 p-6 shadow-lg",
+                // clicks inside the overlay shouldn't close it
+                onclick: move |evt| evt.stop_propagation(),
+                div {
+                    class: "flex justify-between gap-x-4",
+                    h2 {
+                        class: "text-lg font-semibold text-ctp-text",
+                        "Keybindings"
+                    }
+                    button {
+                        r#type: "button",
+                        class: "font-semibold text-ctp-text cursor-pointer",
+                        onclick: move |_| close(),
+                        "✕"
+                    }
+                }
+                table {
+                    class: "mt-4 w-full text-sm",
+                    tbody {
+                        for action in Action::ALL {
+                            tr {
+                                td {
+                                    class: "py-1 pr-6 text-ctp-subtext0",
+                                    {action.description()}
+                                }
+                                td {
+                                    class: "py-1 font-mono text-ctp-text",
+                                    {keybindings.combos(action).iter().join(", ")}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,11 +1,15 @@
 pub mod align_buttons;
 pub mod app_header;
+pub mod broken_banner;
 pub mod button;
 pub mod dropdown;
+pub mod help_overlay;
 pub mod preview;
 pub mod ratio_buttons;
 pub mod save_button;
 pub mod slider;
+pub mod strategy_buttons;
+pub mod unsaved_prompt;
 pub mod wallpaper_button;
 
 pub fn use_ui() -> dioxus::signals::Signal<crate::state::UiState> {
@@ -8,47 +8,47 @@ use dioxus::{
         tao::platform::unix::WindowExtUnix,
         wry::dpi::Position,
     },
+    html::input_data::MouseButton,
     prelude::*,
 };
-use indexmap::IndexMap;
 use itertools::Itertools;
-use wallfacer::{
-    cropper::{Cropper, Direction},
-    geometry::Geometry,
-    wallpapers::WallInfo,
-};
+use std::time::{Duration, Instant};
+use wallfacer::{cropper::Direction, geometry::Geometry, wallpapers::WallInfo};
 
+/// styles for the top, bottom, left and right overlays surrounding the crop, the left and right
+/// overlays only span the height of the crop so they don't overlap the top and bottom overlays
 fn get_overlay_styles(
     img_w: f64,
     img_h: f64,
     elem_w: f64,
     elem_h: f64,
-    direction: Direction,
     geom: &Geometry,
-) -> (String, String) {
+) -> [String; 4] {
     let dimensions = format!("width: {elem_w}px; height: {elem_h}px");
-    match direction {
-        Direction::X => (
-            format!(
-                "transform-origin: left; transform: scaleX({}); {dimensions}",
-                f64::from(geom.x) / img_w,
-            ),
-            format!(
-                "transform-origin: right; transform: scaleX({}); {dimensions}",
-                (img_w - f64::from(geom.x + geom.w)) / img_w,
-            ),
+    let band = format!(
+        "width: {elem_w}px; height: {}px",
+        f64::from(geom.h) / img_h * elem_h
+    );
+    let band_offset = f64::from(geom.y) / img_h * elem_h;
+
+    [
+        format!(
+            "transform-origin: top; transform: scaleY({}); {dimensions}",
+            f64::from(geom.y) / img_h,
         ),
-        Direction::Y => (
-            format!(
-                "transform-origin: top; transform: scaleY({}); {dimensions}",
-                f64::from(geom.y) / img_h,
-            ),
-            format!(
-                "transform-origin: bottom; transform: scaleY({}); {dimensions}",
-                (img_h - f64::from(geom.y + geom.h)) / img_h,
-            ),
+        format!(
+            "transform-origin: bottom; transform: scaleY({}); {dimensions}",
+            (img_h - f64::from(geom.ymax())) / img_h,
         ),
-    }
+        format!(
+            "transform-origin: left; transform: translateY({band_offset}px) scaleX({}); {band}",
+            f64::from(geom.x) / img_w,
+        ),
+        format!(
+            "transform-origin: right; transform: translateY({band_offset}px) scaleX({}); {band}",
+            (img_w - f64::from(geom.xmax())) / img_w,
+        ),
+    ]
 }
 
 fn show_context_menu(x: f64, y: f64, face: &Geometry) {
@@ -71,8 +71,207 @@ fn show_context_menu(x: f64, y: f64, face: &Geometry) {
     );
 }
 
+/// shows the face menu if the mouse event is over a face
+fn show_face_menu_at(evt: &MouseEvent, info: &WallInfo, (elem_w, elem_h): (f64, f64)) {
+    let (x, y) = evt.element_coordinates().into();
+    let (img_w, img_h) = info.dimensions_f64();
+
+    // normalize to absolute image coordinates
+    let img_x = x / elem_w * img_w;
+    let img_y = y / elem_h * img_h;
+
+    if let Some(face) = info
+        .faces
+        .iter()
+        .find(|face| face.contains(img_x as u32, img_y as u32))
+    {
+        let (x, y) = evt.client_coordinates().into();
+        show_context_menu(x, y, face);
+    }
+}
+
+/// scale of the crop for each step of the scroll wheel
+pub const ZOOM_STEP: f64 = 1.1;
+/// zooms closer together than this are undone together
+const ZOOM_HISTORY_DELAY: Duration = Duration::from_millis(500);
+
+/// size of the face resize handles in pixels
+const HANDLE_SIZE: f64 = 10.0;
+/// drawn faces smaller than this are assumed to be accidental clicks
+const MIN_FACE_SIZE: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FaceDrag {
+    /// drawing a new face from the anchor
+    Create { anchor: (f64, f64) },
+    /// offset is from the cursor to the top left of the face
+    Move { index: usize, offset: (f64, f64) },
+    /// anchor is the corner opposite the dragged handle
+    Resize { index: usize, anchor: (f64, f64) },
+}
+
+/// face spanning the two points, clamped to the image
+fn face_from_corners(a: (f64, f64), b: (f64, f64), img_w: f64, img_h: f64) -> Geometry {
+    let (x1, x2) = (a.0.min(b.0).max(0.0), a.0.max(b.0).min(img_w));
+    let (y1, y2) = (a.1.min(b.1).max(0.0), a.1.max(b.1).min(img_h));
+
+    Geometry {
+        w: (x2 - x1) as u32,
+        h: (y2 - y1) as u32,
+        x: x1 as u32,
+        y: y1 as u32,
+    }
+}
+
+/// handles take precedence over the faces themselves, as they overlap the face borders
+fn face_drag_at(faces: &[Geometry], x: f64, y: f64, tolerance: f64) -> Option<FaceDrag> {
+    let handle = faces.iter().enumerate().find_map(|(index, face)| {
+        let (x1, y1) = (f64::from(face.x), f64::from(face.y));
+        let (x2, y2) = (f64::from(face.xmax()), f64::from(face.ymax()));
+
+        [
+            ((x1, y1), (x2, y2)),
+            ((x2, y1), (x1, y2)),
+            ((x1, y2), (x2, y1)),
+            ((x2, y2), (x1, y1)),
+        ]
+        .into_iter()
+        .find(|((cx, cy), _)| (cx - x).abs() <= tolerance && (cy - y).abs() <= tolerance)
+        .map(|(_, anchor)| FaceDrag::Resize { index, anchor })
+    });
+
+    handle.or_else(|| {
+        faces
+            .iter()
+            .position(|face| face.contains(x as u32, y as u32))
+            .map(|index| FaceDrag::Move {
+                index,
+                offset: (x - f64::from(faces[index].x), y - f64::from(faces[index].y)),
+            })
+    })
+}
+
+/// layer over the image for drawing, moving and resizing faces
+#[component]
+fn FaceEditor(wall: Signal<Wall>, elem_wh: (f64, f64)) -> Element {
+    let mut drag = use_signal::<Option<FaceDrag>>(|| None);
+    // the checkpoint is taken once the drag changes something, so a click keeps the redo history
+    let mut needs_checkpoint = use_signal(|| false);
+
+    let (img_w, img_h) = wall().current.dimensions_f64();
+    let (elem_w, elem_h) = elem_wh;
+    let to_img_coords = move |(x, y): (f64, f64)| (x / elem_w * img_w, y / elem_h * img_h);
+
+    rsx! {
+        div {
+            class: "absolute inset-0 z-10 cursor-crosshair",
+            onmousedown: move |evt| {
+                if evt.trigger_button() != Some(MouseButton::Primary) {
+                    return;
+                }
+
+                let (x, y) = to_img_coords(evt.element_coordinates().into());
+                let tolerance = HANDLE_SIZE / elem_w * img_w;
+                let new_drag = face_drag_at(&wall().current.faces, x, y, tolerance)
+                    .unwrap_or(FaceDrag::Create { anchor: (x, y) });
+
+                // the entire drag is undone as a single change
+                needs_checkpoint.set(true);
+                drag.set(Some(new_drag));
+            },
+            onmousemove: move |evt| {
+                let Some(current_drag) = drag() else {
+                    return;
+                };
+                if !evt.held_buttons().contains(MouseButton::Primary) {
+                    return;
+                }
+
+                let pos = to_img_coords(evt.element_coordinates().into());
+                let mut faces = wall().current.faces;
+
+                match current_drag {
+                    FaceDrag::Create { anchor } => {
+                        let face = face_from_corners(anchor, pos, img_w, img_h);
+                        if face.w == 0 || face.h == 0 {
+                            return;
+                        }
+
+                        drag.set(Some(FaceDrag::Resize { index: faces.len(), anchor }));
+                        faces.push(face);
+                    }
+                    FaceDrag::Move { index, offset } => {
+                        let face = &mut faces[index];
+                        face.x = (pos.0 - offset.0).clamp(0.0, img_w - f64::from(face.w)) as u32;
+                        face.y = (pos.1 - offset.1).clamp(0.0, img_h - f64::from(face.h)) as u32;
+                    }
+                    FaceDrag::Resize { index, anchor } => {
+                        faces[index] = face_from_corners(anchor, pos, img_w, img_h);
+                    }
+                }
+
+                wall.with_mut(|wall| {
+                    if needs_checkpoint() && faces != wall.current.faces {
+                        wall.checkpoint();
+                        needs_checkpoint.set(false);
+                    }
+                    wall.update_faces(faces);
+                });
+            },
+            onmouseup: move |_| {
+                if let Some(FaceDrag::Resize { index, .. }) = drag() {
+                    let faces = wall().current.faces;
+
+                    if faces[index].w < MIN_FACE_SIZE || faces[index].h < MIN_FACE_SIZE {
+                        let new_faces = faces
+                            .into_iter()
+                            .enumerate()
+                            .filter_map(|(i, face)| (i != index).then_some(face))
+                            .collect_vec();
+                        wall.with_mut(|wall| wall.update_faces(new_faces));
+                    }
+                }
+
+                drag.set(None);
+            },
+            oncontextmenu: move |evt| {
+                show_face_menu_at(&evt, &wall().current, elem_wh);
+            },
+        }
+    }
+}
+
+/// outlines the area shown on each monitor of the span layout, which move together with the crop
+#[component]
+fn SpanOverlay(wall: Signal<Wall>, geom: Geometry) -> Element {
+    let Some(layout) = wall().span else {
+        return rsx! {};
+    };
+
+    let (img_w, img_h) = wall().current.dimensions_f64();
+    rsx! {
+        {layout.monitor_crops(&geom).into_iter().map(|(monitor, rect)| {
+            let left = f64::from(rect.x) / img_w * 100.0;
+            let top = f64::from(rect.y) / img_h * 100.0;
+            let w = f64::from(rect.w) / img_w * 100.0;
+            let h = f64::from(rect.h) / img_h * 100.0;
+
+            rsx! {
+                div {
+                    class: "absolute border-2 border-dashed border-ctp-blue pointer-events-none",
+                    style: "left: {left}%; top: {top}%; width: {w}%; height: {h}%;",
+                    span {
+                        class: "absolute top-1 left-1 rounded px-1 text-xs bg-ctp-blue text-ctp-base",
+                        "{monitor.name}"
+                    }
+                }
+            }
+        })}
+    }
+}
+
 #[component]
-fn FacesOverlay(wall: Signal<Wall>, direction: Direction) -> Element {
+fn FacesOverlay(wall: Signal<Wall>, editing: bool) -> Element {
     dioxus::desktop::use_muda_event_handler(move |evt| {
         if let Some((id, face)) = evt.id().as_ref().split_once('|') {
             match id {
@@ -85,8 +284,6 @@ fn FacesOverlay(wall: Signal<Wall>, direction: Direction) -> Element {
                     }
                 }
                 "remove-face" => {
-                    let current = wall().current;
-                    let cropper = current.cropper();
                     let new_faces = wall()
                         .current
                         .faces
@@ -95,27 +292,7 @@ fn FacesOverlay(wall: Signal<Wall>, direction: Direction) -> Element {
                         .collect_vec();
 
                     // remove the face and recrop
-                    let new_cropper = Cropper::new(&new_faces, current.width, current.height);
-                    let new_geometries: IndexMap<_, _> = current
-                        .geometries
-                        .into_iter()
-                        .map(|(ratio, geom)| {
-                            // using the default crop, use the new default with the face removed
-                            if geom == cropper.crop(&ratio) {
-                                (ratio.clone(), new_cropper.crop(&ratio))
-                            } else {
-                                (ratio, geom)
-                            }
-                        })
-                        .collect();
-
-                    wall.with_mut(|wallpaper| {
-                        wallpaper.current = WallInfo {
-                            faces: new_faces,
-                            geometries: new_geometries,
-                            ..wallpaper.current.clone()
-                        };
-                    });
+                    wall.with_mut(|wallpaper| wallpaper.set_faces(new_faces));
                 }
                 _ => {}
             }
@@ -141,6 +318,15 @@ fn FacesOverlay(wall: Signal<Wall>, direction: Direction) -> Element {
                     // pointer-events: none to allow mouse events to pass through
                     class: "absolute border-2 bg-transparent border-red-500 inset-0 pointer-events-none transform-gpu origin-top-left",
                     style: format!("width: {w}%; height: {h}%; top: {start_y}%; left: {start_x}%;"),
+
+                    if editing {
+                        for (left, top) in [(0, 0), (100, 0), (0, 100), (100, 100)] {
+                            div {
+                                class: "absolute bg-red-500 -translate-x-1/2 -translate-y-1/2",
+                                style: format!("width: {HANDLE_SIZE}px; height: {HANDLE_SIZE}px; left: {left}%; top: {top}%;"),
+                            }
+                        }
+                    }
                 }
             }
         })}
@@ -151,6 +337,11 @@ fn FacesOverlay(wall: Signal<Wall>, direction: Direction) -> Element {
 pub fn Previewer(wall: Signal<Wall>) -> Element {
     let mut is_dragging = use_signal(|| false);
     let mut dragger = use_signal::<(f64, f64)>(|| (0.0, 0.0));
+    // corner opposite the resize handle being dragged, in image coordinates
+    let mut resize_anchor = use_signal::<Option<(f64, f64)>>(|| None);
+    // the checkpoint is taken once the drag changes the crop, so a click keeps the redo history
+    let mut needs_checkpoint = use_signal(|| false);
+    let mut last_zoom = use_signal::<Option<Instant>>(|| None);
     let mut elem_wh = use_signal(|| (0.0, 0.0));
 
     let ui = use_ui();
@@ -162,16 +353,10 @@ pub fn Previewer(wall: Signal<Wall>) -> Element {
 
     let (img_w, img_h) = wall().current.dimensions_f64();
 
-    // get direction of the geometry
-    let direction = if wall().current.height == geom.h {
-        Direction::X
-    } else {
-        Direction::Y
-    };
-
-    let cursor_cls = match direction {
-        Direction::X => "cursor-ew-resize",
-        Direction::Y => "cursor-ns-resize",
+    let cursor_cls = match wall().current.direction(&geom) {
+        Some(Direction::X) => "cursor-ew-resize",
+        Some(Direction::Y) => "cursor-ns-resize",
+        None => "cursor-move",
     };
 
     let pointer_cls = if is_dragging() {
@@ -180,18 +365,30 @@ pub fn Previewer(wall: Signal<Wall>) -> Element {
         ""
     };
 
-    let (start_overlay_style, end_overlay_style) =
-        get_overlay_styles(img_w, img_h, elem_wh().0, elem_wh().1, direction, &geom);
+    let overlay_styles = get_overlay_styles(img_w, img_h, elem_wh().0, elem_wh().1, &geom);
 
+    // portrait images are limited by the height of the pane instead of the width
+    let portrait_cls = if img_h > img_w { "h-full" } else { "" };
     let overlay_cls =
         "absolute bg-black/60 inset-0 transform-gpu isolate transition will-change-transform";
 
+    // (left %, top %, anchor, cursor) for each corner of the crop
+    let (x1, y1) = (f64::from(geom.x), f64::from(geom.y));
+    let (x2, y2) = (f64::from(geom.xmax()), f64::from(geom.ymax()));
+    let handles = [
+        ((x1, y1), (x2, y2), "cursor-nwse-resize"),
+        ((x2, y1), (x1, y2), "cursor-nesw-resize"),
+        ((x1, y2), (x2, y1), "cursor-nesw-resize"),
+        ((x2, y2), (x1, y1), "cursor-nwse-resize"),
+    ]
+    .map(|((x, y), anchor, cursor)| (x / img_w * 100.0, y / img_h * 100.0, anchor, cursor));
+
     rsx! {
         div {
             class: "flex items-center justify-center min-h-0 min-w-0 px-4 pb-4 {cursor_cls}",
 
             div {
-                class: "relative m-auto max-h-full max-w-full",
+                class: "relative m-auto max-h-full max-w-full {portrait_cls}",
                 style: "aspect-ratio: {wall().current.width} / {wall().current.height};",
 
                 img {
@@ -207,75 +404,103 @@ pub fn Previewer(wall: Signal<Wall>) -> Element {
                         async move {
                             let (x, y) = evt.element_coordinates().into();
 
+                            // the entire drag is undone as a single move
+                            needs_checkpoint.set(true);
                             is_dragging.set(true);
                             dragger.set((x, y));
                         }
                     },
                     oncontextmenu: move |evt| {
-                        let (x, y) = evt.element_coordinates().into();
-                        let (elem_w, elem_h) = elem_wh();
-
-                        // normalize to absolute image coordinates
-                        let img_x = x / elem_w * img_w;
-                        let img_y = y / elem_h * img_h;
-
-                        if let Some(face) = wall().current.faces.iter().find(|face|
-                            face.contains(img_x as u32, img_y as u32)
-                        ) {
-                            let (x, y) = evt.client_coordinates().into();
-                            show_context_menu(x, y, face);
-                        }
-
+                        show_face_menu_at(&evt, &wall().current, elem_wh());
                     },
                     onmouseup: move |_| {
                         is_dragging.set(false);
+                        resize_anchor.set(None);
                     },
                     onmousemove: move |evt| {
-                        if is_dragging() && evt.held_buttons().contains(dioxus::html::input_data::MouseButton::Primary) {
+                        if is_dragging() && evt.held_buttons().contains(MouseButton::Primary) {
                             let (new_x, new_y) = evt.element_coordinates().into();
                             let (x, y) = dragger();
                             let (elem_w, elem_h) = elem_wh();
 
-                            let new_geom = match direction {
-                                Direction::X => {
-                                    let dx = img_w / elem_w * (new_x - x);
-                                    Geometry {
-                                        x: (f64::from(geom.x) + dx).clamp(0.0, img_w - f64::from(geom.w)) as u32,
-                                        ..geom.clone()
-                                    }
-                                }
-                                Direction::Y => {
-                                    let dy = img_h / elem_h * (new_y - y);
-                                    Geometry {
-                                        y: (f64::from(geom.y) + dy).clamp(0.0, img_h - f64::from(geom.h)) as u32,
-                                        ..geom.clone()
-                                    }
-                                }
+                            let new_geom = if let Some(anchor) = resize_anchor() {
+                                wall().resize_geometry_to(anchor, (new_x / elem_w * img_w, new_y / elem_h * img_h))
+                            } else {
+                                wall().move_geometry_by(img_w / elem_w * (new_x - x), img_h / elem_h * (new_y - y))
                             };
 
                             wall.with_mut(|wallpaper| {
-                                wallpaper.set_current_geometry(&new_geom);
+                                if needs_checkpoint() && new_geom != wallpaper.get_current_geometry() {
+                                    wallpaper.checkpoint();
+                                    needs_checkpoint.set(false);
+                                }
+                                wallpaper.update_current_geometry(&new_geom);
                             });
 
                             dragger.set((new_x, new_y));
                         }
                     },
+                    onwheel: move |evt| {
+                        let (x, y) = evt.element_coordinates().into();
+                        let (elem_w, elem_h) = elem_wh();
+
+                        // scrolling down zooms out
+                        let scale = if evt.delta().strip_units().y > 0.0 {
+                            ZOOM_STEP
+                        } else {
+                            1.0 / ZOOM_STEP
+                        };
+                        let new_geom = wall().zoom_geometry_by(scale, (x / elem_w * img_w, y / elem_h * img_h));
+
+                        // zooming past the limits doesn't change the crop, so the redo history is kept
+                        if new_geom == wall().get_current_geometry() {
+                            return;
+                        }
+
+                        // continuous scrolling is undone as a single zoom
+                        if last_zoom().is_none_or(|last| last.elapsed() > ZOOM_HISTORY_DELAY) {
+                            wall.with_mut(Wall::checkpoint);
+                        }
+                        last_zoom.set(Some(Instant::now()));
+
+                        wall.with_mut(|wallpaper| {
+                            wallpaper.update_current_geometry(&new_geom);
+                        });
+                    },
                 }
 
-                // start overlay
-                div {
-                    class: "{overlay_cls} {pointer_cls}",
-                    style: start_overlay_style,
+                for style in overlay_styles {
+                    div {
+                        class: "{overlay_cls} {pointer_cls}",
+                        style,
+                    }
                 }
 
-                // end overlay
-                div {
-                    class: "{overlay_cls} {pointer_cls}",
-                    style: end_overlay_style,
+                SpanOverlay { wall, geom: geom.clone() }
+
+                if !ui().edit_faces {
+                    for (left, top, anchor, cursor) in handles {
+                        div {
+                            class: "absolute z-10 size-3 rounded-sm bg-white -translate-x-1/2 -translate-y-1/2 {cursor} {pointer_cls}",
+                            style: "left: {left}%; top: {top}%;",
+                            onmousedown: move |evt| {
+                                evt.stop_propagation();
+
+                                // the entire resize is undone as a single change
+                                needs_checkpoint.set(true);
+                                resize_anchor.set(Some(anchor));
+                                is_dragging.set(true);
+                            },
+                        }
+                    }
+                }
+
+                if ui().show_faces || ui().edit_faces {
+                    FacesOverlay { wall, editing: ui().edit_faces }
                 }
 
-                if ui().show_faces {
-                    FacesOverlay { wall, direction }
+                if ui().edit_faces {
+                    FaceEditor { wall, elem_wh: elem_wh() }
                 }
             }
         }
@@ -3,11 +3,18 @@
 use dioxus::prelude::*;
 
 use crate::{components::button::PreviewableButton, state::Wall};
-use wallfacer::aspect_ratio::AspectRatio;
+use wallfacer::{aspect_ratio::AspectRatio, layout::SpanLayout};
 
 pub fn change_ratio(wall: &mut Signal<Wall>, ratio: &AspectRatio) {
     wall.with_mut(|wall| {
         wall.ratio = ratio.clone();
+        wall.span = None;
+    });
+}
+
+pub fn change_span(wall: &mut Signal<Wall>, layout: &SpanLayout) {
+    wall.with_mut(|wall| {
+        wall.span = Some(layout.clone());
     });
 }
 
@@ -17,6 +24,9 @@ pub fn RatioButtons(wall: Signal<Wall>, class: Option<String>) -> Element {
 
     let len = ratios.len();
 
+    let cropper = wall().cropper();
+    let source_cropper = wall().source_cropper();
+
     let buttons = ratios.into_iter().enumerate().map(|(i, res)| {
         let cls = if i == 0 {
             "rounded-l-md"
@@ -26,8 +36,13 @@ pub fn RatioButtons(wall: Signal<Wall>, class: Option<String>) -> Element {
             "-ml-px"
         };
 
-        let current_geom = wall().current.get_geometry(&res.resolution);
-        let dirty_marker = if current_geom == wall().source.get_geometry(&res.resolution) {
+        let current_geom = wall().current.get_geometry(&res.resolution, &cropper);
+        let upscale_needed = res.upscale_needed(&current_geom);
+        let dirty_marker = if current_geom
+            == wall()
+                .source
+                .get_geometry(&res.resolution, &source_cropper)
+        {
             " "
         } else {
             "*"
@@ -39,7 +54,7 @@ pub fn RatioButtons(wall: Signal<Wall>, class: Option<String>) -> Element {
                 geom: current_geom,
                 wall,
                 title: res.description,
-                active: wall().ratio == res.resolution,
+                active: wall().span.is_none() && wall().ratio == res.resolution,
                 onclick: move |_| {
                     change_ratio(&mut wall, &res.resolution);
                 },
@@ -47,14 +62,70 @@ pub fn RatioButtons(wall: Signal<Wall>, class: Option<String>) -> Element {
                     class: "whitespace-pre",
                     "  {res.name} {dirty_marker}"
                 }
+                // crop is smaller than the monitor
+                if let Some(scale) = upscale_needed {
+                    span {
+                        class: "ml-1 rounded px-1 text-xs bg-ctp-red text-ctp-base",
+                        title: format!("{scale:.1}x upscale needed for {}x{}", res.width, res.height),
+                        "{scale:.1}x"
+                    }
+                }
+            }
+        }
+    });
+
+    let layouts = wall().layouts;
+    let layouts_len = layouts.len();
+
+    let span_buttons = layouts.into_iter().enumerate().map(|(i, layout)| {
+        let cls = match (i == 0, i == layouts_len - 1) {
+            (true, true) => "rounded-md",
+            (true, false) => "rounded-l-md",
+            (false, true) => "rounded-r-md -ml-px",
+            (false, false) => "-ml-px",
+        };
+
+        let current_geom = wall().current.get_span(&layout, &cropper);
+        let dirty_marker = if current_geom == wall().source.get_span(&layout, &source_cropper) {
+            " "
+        } else {
+            "*"
+        };
+
+        rsx! {
+            PreviewableButton {
+                class: "text-sm {cls}",
+                geom: current_geom,
+                wall,
+                title: layout.description.clone(),
+                active: wall().span.as_ref() == Some(&layout),
+                onclick: move |_| {
+                    change_span(&mut wall, &layout);
+                },
+                span {
+                    class: "whitespace-pre",
+                    "  {layout.name} {dirty_marker}"
+                }
             }
         }
     });
 
     rsx! {
-        span {
-            class: "isolate inline-flex rounded-md shadow-sm",
-            {buttons}
+        // a single grid cell in the editor
+        div {
+            class: "flex flex-wrap items-center gap-4",
+
+            span {
+                class: "isolate inline-flex rounded-md shadow-sm",
+                {buttons}
+            }
+
+            if layouts_len > 0 {
+                span {
+                    class: "isolate inline-flex rounded-md shadow-sm",
+                    {span_buttons}
+                }
+            }
         }
     }
 }
@@ -10,8 +10,8 @@ pub fn save_image(wall: &Wall, wallpapers: &mut Signal<Wallpapers>) {
     let mut ui = use_ui();
 
     wallpapers.with_mut(|wallpapers| {
-        wall.current
-            .save()
+        wallpapers
+            .save(&wall.saved_info())
             .unwrap_or_else(|_| panic!("could not save {}", wall.current.path.display()));
         wallpapers.remove();
     });
@@ -0,0 +1,71 @@
+#![allow(non_snake_case)]
+
+use dioxus::prelude::*;
+use wallfacer::crop_strategy::CropStrategyKind;
+
+use crate::{
+    components::{
+        button::{Button, PreviewableButton},
+        use_ui,
+    },
+    state::Wall,
+};
+
+#[component]
+pub fn StrategyButtons(wall: Signal<Wall>) -> Element {
+    let mut ui = use_ui();
+    let current_geom = wall().get_current_geometry();
+    let configured = wall().crop.strategy;
+    let len = CropStrategyKind::ALL.len();
+
+    let buttons = CropStrategyKind::ALL
+        .into_iter()
+        .enumerate()
+        .map(|(i, strategy)| {
+            let cls = if i == len - 1 { "rounded-r-md" } else { "" };
+            let geom = wall().get_strategy_geometry(strategy);
+            let label = strategy.label();
+            let marker = if strategy == configured { "*" } else { "" };
+
+            rsx! {
+                PreviewableButton {
+                    class: "text-sm -ml-px {cls}",
+                    geom: geom.clone(),
+                    wall,
+                    title: "Use the {label} crop strategy",
+                    active: current_geom == geom,
+                    onclick: move |_| {
+                        wall.with_mut(|wall| wall.set_current_geometry(&geom));
+                    },
+                    "{label}{marker}"
+                }
+            }
+        });
+
+    let compare_cls = if ui().compare_strategies {
+        "rounded-l-md"
+    } else {
+        "rounded-md"
+    };
+
+    rsx! {
+        span {
+            class: "isolate inline-flex rounded-md shadow-sm",
+            Button {
+                class: "text-sm {compare_cls}",
+                title: "Compare crop strategies",
+                active: ui().compare_strategies,
+                onclick: move |_| {
+                    ui.with_mut(|ui| {
+                        ui.compare_strategies = !ui.compare_strategies;
+                    });
+                },
+                "Compare"
+            }
+
+            if ui().compare_strategies {
+                {buttons}
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+#![allow(non_snake_case)]
+use dioxus::prelude::*;
+
+use crate::{
+    components::use_ui,
+    state::{Navigation, UiMode, UiState, Wall, Wallpapers},
+};
+use wallfacer::{
+    config::{Config, UnsavedPolicy},
+    filename,
+};
+
+/// saves the changes without removing the wallpaper from the list
+pub fn save_changes(wall: &mut Signal<Wall>, wallpapers: &Signal<Wallpapers>) {
+    let current = wall.peek().saved_info();
+
+    wallpapers
+        .peek()
+        .save(&current)
+        .unwrap_or_else(|e| panic!("could not save {}: {e}", current.path.display()));
+    wall.with_mut(Wall::mark_saved);
+}
+
+fn apply_navigation(
+    nav: Navigation,
+    wallpapers: &mut Signal<Wallpapers>,
+    ui: &mut Signal<UiState>,
+) {
+    match nav {
+        Navigation::Prev => wallpapers.with_mut(Wallpapers::prev_wall),
+        Navigation::Next => wallpapers.with_mut(Wallpapers::next_wall),
+        Navigation::File(fname) => {
+            wallpapers.with_mut(|wallpapers| wallpapers.set_from_filename(&fname));
+        }
+        Navigation::Close => {
+            dioxus::desktop::window().close();
+            return;
+        }
+    }
+
+    ui.with_mut(|ui| {
+        if ui.mode == UiMode::FileList {
+            ui.mode = UiMode::Editor;
+        }
+    });
+}
+
+/// leaves the current wallpaper, handling unsaved changes according to the config
+pub fn navigate(nav: Navigation, wall: &mut Signal<Wall>, wallpapers: &mut Signal<Wallpapers>) {
+    let mut ui = use_ui();
+    let cfg = use_context::<Signal<Config>>();
+
+    if wall.peek().is_dirty() {
+        match cfg.peek().unsaved_changes {
+            UnsavedPolicy::Prompt => {
+                ui.with_mut(|ui| {
+                    ui.pending_navigation = Some(nav);
+                });
+                return;
+            }
+            UnsavedPolicy::AutoSave => save_changes(wall, wallpapers),
+            UnsavedPolicy::Discard => wall.with_mut(Wall::discard_changes),
+        }
+    }
+
+    apply_navigation(nav, wallpapers, &mut ui);
+}
+
+#[component]
+pub fn UnsavedPrompt(wall: Signal<Wall>, wallpapers: Signal<Wallpapers>) -> Element {
+    let mut ui = use_ui();
+
+    let Some(nav) = ui().pending_navigation else {
+        return rsx! {};
+    };
+
+    let mut resolve = move |save: Option<bool>| {
+        let nav = ui.with_mut(|ui| ui.pending_navigation.take());
+
+        match (save, nav) {
+            (Some(true), Some(nav)) => {
+                save_changes(&mut wall, &wallpapers);
+                apply_navigation(nav, &mut wallpapers, &mut ui);
+            }
+            (Some(false), Some(nav)) => {
+                wall.with_mut(Wall::discard_changes);
+                apply_navigation(nav, &mut wallpapers, &mut ui);
+            }
+            // cancelled
+            _ => {}
+        }
+    };
+
+    let btn_cls = "rounded-md px-3 py-2 text-sm font-semibold text-white shadow-sm cursor-pointer";
+    let action = if nav == Navigation::Close {
+        "closing"
+    } else {
+        "leaving"
+    };
+
+    rsx! {
+        div {
+            class: "fixed inset-0 z-30 flex items-center justify-center bg-ctp-crust/70",
+            div {
+                class: "max-w-md rounded-md bg-ctp-surface0 p-6 shadow-lg",
+                h2 {
+                    class: "text-lg font-semibold text-ctp-text",
+                    "Unsaved changes"
+                }
+                p {
+                    class: "mt-2 text-sm text-ctp-subtext0",
+                    "{filename(&wall().current.path)} has crop changes that have not been saved. Save them before {action}?"
+                }
+                div {
+                    class: "mt-6 flex justify-end gap-x-3",
+                    button {
+                        r#type: "button",
+                        class: "{btn_cls} bg-ctp-surface1 hover:bg-ctp-crust",
+                        onclick: move |_| resolve(None),
+                        "Cancel"
+                    }
+                    button {
+                        r#type: "button",
+                        class: "{btn_cls} bg-ctp-red hover:bg-ctp-maroon",
+                        onclick: move |_| resolve(Some(false)),
+                        "Discard"
+                    }
+                    button {
+                        r#type: "button",
+                        class: "{btn_cls} bg-indigo-600 hover:bg-indigo-500",
+                        autofocus: true,
+                        onclick: move |_| resolve(Some(true)),
+                        "Save"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+
+use image::{ImageReader, imageops::FilterType};
+use itertools::Itertools;
+use wallfacer::{
+    PathBufVecExt, cli::DetectArgs, config::Config, detector::FaceDetector, geometry::Geometry,
+    index::WallIndex, report_failures, run_wallfacer, wallpapers::WallInfo,
+    workspace::TempWorkspace,
+};
+
+/// detects the faces at the original size of the wallpaper, as detection in the upscaled image
+/// is slower and the faces were originally detected before upscaling
+fn detect_faces(
+    info: &WallInfo,
+    cfg: &Config,
+    workspace: &TempWorkspace,
+) -> Result<Vec<Geometry>, String> {
+    let scale = info.scale.unwrap_or(1).max(1);
+    if scale == 1 {
+        return cfg.detector.detect(&info.path).map_err(|e| e.to_string());
+    }
+
+    let downscaled = workspace.path_for(&info.path).map_err(|e| e.to_string())?;
+    ImageReader::open(&info.path)
+        .map_err(|e| e.to_string())?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .decode()
+        .map_err(|e| e.to_string())?
+        .resize_exact(
+            info.width / scale,
+            info.height / scale,
+            FilterType::Lanczos3,
+        )
+        .save(&downscaled)
+        .map_err(|e| e.to_string())?;
+
+    let faces = cfg.detector.detect(&downscaled).map_err(|e| e.to_string());
+    workspace.remove(&downscaled).map_err(|e| e.to_string())?;
+
+    Ok(faces?.into_iter().map(|face| face * scale).collect())
+}
+
+pub fn main(config_path: Option<PathBuf>, args: &DetectArgs) {
+    let cfg = Config::new(config_path).expect("failed to load config");
+    let mut all_files = args.paths.filter_wallpapers();
+    all_files.numeric_sort();
+
+    let workspace = TempWorkspace::new("detect").unwrap_or_else(|e| {
+        eprintln!("Unable to create temporary directory: {e}");
+        std::process::exit(1);
+    });
+
+    let mut index = WallIndex::load(&cfg.metadata);
+    let mut changed = Vec::new();
+    let mut failed = Vec::new();
+
+    for path in &all_files {
+        let mut info = match index.get(path) {
+            Ok(info) => info,
+            Err(e) => {
+                failed.push((path, e.to_string()));
+                continue;
+            }
+        };
+
+        let faces = match detect_faces(&info, &cfg, &workspace) {
+            Ok(faces) => faces,
+            Err(e) => {
+                failed.push((path, e));
+                continue;
+            }
+        };
+
+        if faces == info.faces {
+            println!("{}: Unchanged", path.display());
+            continue;
+        }
+
+        println!(
+            "{}: {} -> {} faces",
+            path.display(),
+            info.faces.len(),
+            faces.len()
+        );
+        let cropper = info.default_cropper(&cfg.crop);
+        info.update_faces(faces, &cropper, &cfg.layouts);
+
+        if let Err(e) = index.save_info(&info) {
+            failed.push((path, e.to_string()));
+            continue;
+        }
+
+        changed.push(path.clone());
+    }
+
+    println!(
+        "\n{} / {} wallpapers have updated faces",
+        changed.len(),
+        all_files.len()
+    );
+
+    report_failures("detected", &failed, all_files.len());
+
+    index.save().unwrap_or_else(|e| {
+        eprintln!("Unable to save wallpaper index: {e}");
+    });
+
+    // review the updated crops in wallfacer
+    if !changed.is_empty() {
+        drop(workspace);
+        run_wallfacer(
+            changed
+                .into_iter()
+                .map(|path| path.display().to_string())
+                .collect_vec(),
+        );
+    }
+}
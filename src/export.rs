@@ -0,0 +1,150 @@
+// exporting cropped wallpapers for each resolution
+
+use std::path::{Path, PathBuf};
+
+use image::{ImageError, ImageReader, imageops::FilterType};
+use itertools::Itertools;
+use rayon::prelude::*;
+use thiserror::Error;
+use wallfacer::{
+    PathBufVecExt,
+    cli::ExportArgs,
+    config::{Config, ConfigResolution},
+    cropper::Cropper,
+    filename,
+    geometry::Geometry,
+    layout::SpanLayout,
+    report_failures,
+    wallpapers::{WallInfo, WallInfoError},
+};
+
+#[derive(Error, Debug)]
+enum ExportError {
+    #[error(transparent)]
+    Info(#[from] WallInfoError),
+    #[error("Unable to read image: {0}")]
+    Read(ImageError),
+    #[error("Unable to save {0}: {1}")]
+    Save(PathBuf, ImageError),
+}
+
+/// a single exported image, either for a resolution or a monitor within a span layout
+enum ExportTarget<'a> {
+    Resolution(&'a ConfigResolution),
+    Monitor(&'a SpanLayout, usize),
+}
+
+impl ExportTarget<'_> {
+    fn all(cfg: &Config) -> Vec<ExportTarget<'_>> {
+        cfg.resolutions
+            .iter()
+            .map(ExportTarget::Resolution)
+            .chain(cfg.layouts.iter().flat_map(|layout| {
+                (0..layout.monitors.len()).map(move |i| ExportTarget::Monitor(layout, i))
+            }))
+            .collect()
+    }
+
+    /// images are written to DEST/<resolution name> or DEST/<layout name>/<monitor name>
+    fn dir(&self, output: &Path) -> PathBuf {
+        match self {
+            Self::Resolution(res) => output.join(&res.name),
+            Self::Monitor(layout, i) => output.join(&layout.name).join(&layout.monitors[*i].name),
+        }
+    }
+
+    /// area of the wallpaper to be cropped and the size it is resized to
+    fn crop(&self, info: &WallInfo, cropper: &Cropper) -> (Geometry, u32, u32) {
+        match self {
+            Self::Resolution(res) => (
+                info.get_geometry(&res.resolution, cropper),
+                res.width,
+                res.height,
+            ),
+            Self::Monitor(layout, i) => {
+                let (monitor, geom) = layout
+                    .monitor_crops(&info.get_span(layout, cropper))
+                    .swap_remove(*i);
+                (geom, monitor.width, monitor.height)
+            }
+        }
+    }
+}
+
+/// checks if the exported image is newer than the source wallpaper
+fn is_up_to_date(src: &Path, dest: &Path) -> bool {
+    let modified = |p: &Path| p.metadata().and_then(|meta| meta.modified()).ok();
+
+    match (modified(src), modified(dest)) {
+        (Some(src_modified), Some(dest_modified)) => dest_modified > src_modified,
+        _ => false,
+    }
+}
+
+fn export_wallpaper(
+    wall: &PathBuf,
+    cfg: &Config,
+    output: &Path,
+    force: bool,
+) -> Result<(), ExportError> {
+    let fname = filename(wall);
+
+    let to_export = ExportTarget::all(cfg)
+        .into_iter()
+        .map(|target| {
+            let dest = target.dir(output).join(&fname);
+            (target, dest)
+        })
+        .filter(|(_, dest)| force || !is_up_to_date(wall, dest))
+        .collect_vec();
+
+    // all exports are newer than the wallpaper
+    if to_export.is_empty() {
+        return Ok(());
+    }
+
+    let info = WallInfo::new_from_file(wall, &cfg.metadata)?;
+    let cropper = info.default_cropper(&cfg.crop);
+    let img = ImageReader::open(wall)
+        .map_err(|e| ExportError::Read(e.into()))?
+        .decode()
+        .map_err(ExportError::Read)?;
+
+    for (target, dest) in to_export {
+        let (geom, width, height) = target.crop(&info, &cropper);
+
+        img.crop_imm(geom.x, geom.y, geom.w, geom.h)
+            .resize_exact(width, height, FilterType::Lanczos3)
+            .save(&dest)
+            .map_err(|e| ExportError::Save(dest.clone(), e))?;
+    }
+
+    Ok(())
+}
+
+pub fn main(config_path: Option<PathBuf>, args: &ExportArgs) {
+    let cfg = Config::new(config_path).expect("failed to load config");
+    let mut all_files = args.paths.filter_wallpapers();
+    all_files.numeric_sort();
+
+    for target in ExportTarget::all(&cfg) {
+        let res_dir = target.dir(&args.output);
+        std::fs::create_dir_all(&res_dir)
+            .unwrap_or_else(|_| panic!("could not create {}", res_dir.display()));
+    }
+
+    let failed = all_files
+        .par_iter()
+        .filter_map(|wall| {
+            println!("Processing: {}", wall.display());
+            export_wallpaper(wall, &cfg, &args.output, args.force)
+                .err()
+                .map(|e| (wall, e))
+        })
+        .collect::<Vec<_>>();
+
+    report_failures("exported", &failed, all_files.len());
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use wallfacer::{
+    PathBufVecExt,
+    cli::{IndexArgs, IndexCommands},
+    config::Config,
+    index::WallIndex,
+    report_failures,
+};
+
+fn rebuild(cfg: &Config, paths: &[PathBuf]) {
+    let mut all_files = paths.to_vec().filter_wallpapers();
+    all_files.numeric_sort();
+
+    let mut index = WallIndex::load(&cfg.metadata);
+    index.prune();
+
+    let mut failed = Vec::new();
+    for path in &all_files {
+        // force the wallpaper to be re-read
+        index.remove(path);
+        if let Err(e) = index.get(path) {
+            failed.push((path, e));
+        }
+    }
+
+    index.save().unwrap_or_else(|e| {
+        eprintln!("Unable to save wallpaper index: {e}");
+        std::process::exit(1);
+    });
+
+    println!(
+        "Indexed {} wallpapers, {} wallpapers in index",
+        all_files.len() - failed.len(),
+        index.len()
+    );
+
+    report_failures("indexed", &failed, all_files.len());
+}
+
+pub fn main(config_path: Option<PathBuf>, args: &IndexArgs) {
+    let cfg = Config::new(config_path).expect("failed to load config");
+
+    match &args.command {
+        IndexCommands::Rebuild(rebuild_args) => rebuild(&cfg, &rebuild_args.paths),
+    }
+}
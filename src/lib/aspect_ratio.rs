@@ -88,17 +88,23 @@ impl From<&AspectRatio> for f64 {
     }
 }
 
+/// parses a string in the format `WxH` into its width and height, without reducing it
+pub fn parse_dimensions(s: &str) -> Result<(u32, u32), AspectRatioError> {
+    let parts = s.split('x').flat_map(str::parse::<u32>).collect_vec();
+
+    if parts.len() != 2 || parts.contains(&0) {
+        return Err(AspectRatioError::InvalidAspectRatio);
+    }
+
+    Ok((parts[0], parts[1]))
+}
+
 impl TryFrom<&str> for AspectRatio {
     type Error = AspectRatioError;
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
-        let parts = s.split('x').flat_map(str::parse::<u32>).collect_vec();
-
-        if parts.len() != 2 {
-            return Err(AspectRatioError::InvalidAspectRatio);
-        }
-
-        Ok(Self::new(parts[0], parts[1]))
+        let (width, height) = parse_dimensions(s)?;
+        Ok(Self::new(width, height))
     }
 }
 
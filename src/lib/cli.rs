@@ -23,6 +23,29 @@ pub struct AddWallpaperArgs {
     #[arg(long, action, help = "Reprocess the image even if it already exists")]
     pub force: bool,
 
+    #[arg(
+        long,
+        short,
+        value_name = "N",
+        help = "Number of images to process in parallel, defaults to the number of cpus"
+    )]
+    pub jobs: Option<usize>,
+
+    #[arg(
+        long,
+        action,
+        help = "Show what would be done to each image without making any changes"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        action,
+        requires = "dry_run",
+        help = "Output the dry run plan as json"
+    )]
+    pub json: bool,
+
     // required positional args for input paths and output directory
     /// input directories or images to add
     #[arg(required = true, num_args = 1.., value_name = "SRC")]
@@ -45,6 +68,162 @@ pub struct AddResolutionArgs {
     pub output: PathBuf,
 }
 
+#[derive(Args, Debug)]
+pub struct RemoveResolutionArgs {
+    /// name or <width>x<height> of the resolution to remove
+    pub name: String,
+
+    /// output directory, needed to strip the crops
+    pub output: Option<PathBuf>,
+
+    #[arg(
+        long,
+        action,
+        requires = "output",
+        help = "Remove the crops for the resolution from the wallpapers in the output directory"
+    )]
+    pub strip: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RenameResolutionArgs {
+    /// name or <width>x<height> of the resolution to rename
+    pub name: String,
+
+    /// the new name of the resolution
+    pub new_name: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ListResolutionArgs {
+    /// output directory, counts the custom and default crops for each resolution
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ResolutionCommands {
+    #[command(name = "add", about = "Adds a new resolution for cropping")]
+    Add(AddResolutionArgs),
+
+    #[command(name = "remove", about = "Removes a resolution")]
+    Remove(RemoveResolutionArgs),
+
+    #[command(name = "rename", about = "Renames a resolution")]
+    Rename(RenameResolutionArgs),
+
+    #[command(
+        name = "list",
+        about = "Lists the resolutions, with the number of custom and default crops"
+    )]
+    List(ListResolutionArgs),
+}
+
+#[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true, arg_required_else_help = true)]
+pub struct ResolutionArgs {
+    #[command(subcommand)]
+    pub command: Option<ResolutionCommands>,
+
+    /// `wallfacer resolution NAME RESOLUTION DEST` from before the subcommands, same as add
+    #[command(flatten)]
+    pub legacy_add: Option<AddResolutionArgs>,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    #[arg(
+        long,
+        action,
+        help = "Export the image even if the exported image is newer than the wallpaper"
+    )]
+    pub force: bool,
+
+    // required positional args for input paths and output directory
+    /// input directories or wallpapers to export
+    #[arg(required = true, num_args = 1.., value_name = "PATHS")]
+    pub paths: Vec<PathBuf>,
+
+    /// output directory, images are written to DEST/<resolution name>/<file>, or
+    /// DEST/<layout name>/<monitor name>/<file> for span layouts
+    #[arg(required = true, value_name = "DEST")]
+    pub output: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    #[arg(
+        long,
+        action,
+        help = "Report crops that are smaller than the resolution of their monitor"
+    )]
+    pub undersized: bool,
+
+    #[arg(help = "Directories or wallpapers to be checked", value_name = "PATHS", required = true, num_args = 1..)]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataTarget {
+    Embedded,
+    Sidecar,
+}
+
+#[derive(Args, Debug)]
+pub struct DetectArgs {
+    #[arg(help = "Directories or wallpapers to detect faces in", value_name = "PATHS", required = true, num_args = 1..)]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct RecropArgs {
+    #[arg(
+        long,
+        action,
+        help = "Only report the crops that would move, without saving them"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        action,
+        help = "Refresh every automatic crop instead of only those from an older cropper, e.g. after changing the crop strategy"
+    )]
+    pub all: bool,
+
+    #[arg(help = "Directories or wallpapers to be recropped", value_name = "PATHS", required = true, num_args = 1..)]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct MigrateArgs {
+    #[arg(long, value_enum, help = "Where the wallfacer data should be stored")]
+    pub to: MetadataTarget,
+
+    #[arg(help = "Directories or wallpapers to be migrated", value_name = "PATHS", required = true, num_args = 1..)]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct IndexRebuildArgs {
+    #[arg(help = "Directories or wallpapers to be indexed", value_name = "PATHS", required = true, num_args = 1..)]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum IndexCommands {
+    #[command(
+        name = "rebuild",
+        about = "Re-reads the wallpapers into the index and removes missing wallpapers"
+    )]
+    Rebuild(IndexRebuildArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct IndexArgs {
+    #[command(subcommand)]
+    pub command: IndexCommands,
+}
+
 #[derive(Parser)]
 #[command(name = "trimmer", about = "Automatic trimming of images")]
 pub struct TrimmerArgs {
@@ -127,8 +306,44 @@ pub enum Commands {
     )]
     Add(AddWallpaperArgs),
 
-    #[command(name = "resolution", about = "Adds a new resolution for cropping")]
-    AddResolution(AddResolutionArgs),
+    #[command(
+        name = "resolution",
+        about = "Manages the resolutions used for cropping"
+    )]
+    Resolution(ResolutionArgs),
+
+    #[command(
+        name = "export",
+        about = "Exports the cropped wallpapers for each resolution"
+    )]
+    Export(ExportArgs),
+
+    #[command(
+        name = "check",
+        about = "Reports problems with the crops of wallpapers"
+    )]
+    Check(CheckArgs),
+
+    #[command(
+        name = "detect",
+        about = "Re-runs face detection on existing wallpapers"
+    )]
+    Detect(DetectArgs),
+
+    #[command(
+        name = "recrop",
+        about = "Recomputes the default crops that were not edited"
+    )]
+    Recrop(RecropArgs),
+
+    #[command(
+        name = "migrate",
+        about = "Moves wallfacer data between embedded xmp and sidecar files"
+    )]
+    Migrate(MigrateArgs),
+
+    #[command(name = "index", about = "Manages the cached index of wallpapers")]
+    Index(IndexArgs),
 
     #[command(name = "gui", about = "Launches the wallfacer GUI")]
     Gui(GuiArgs),
@@ -0,0 +1,46 @@
+use std::process::{Command, ExitStatus, Stdio};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CommandError {
+    #[error("Unable to run {0}: {1}")]
+    Spawn(String, std::io::Error),
+    #[error("{0} exited with {1}")]
+    Failed(String, ExitStatus),
+}
+
+/// runs an external tool to completion with stderr silenced, returning its stdout
+pub fn run_command(cmd: &mut Command, name: &str) -> Result<Vec<u8>, CommandError> {
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| CommandError::Spawn(name.to_string(), e))?;
+
+    if !output.status.success() {
+        return Err(CommandError::Failed(name.to_string(), output.status));
+    }
+
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_command() {
+        let stdout =
+            run_command(Command::new("echo").arg("faces"), "echo").expect("echo should succeed");
+        assert_eq!(stdout, b"faces\n");
+
+        assert!(matches!(
+            run_command(&mut Command::new("false"), "false"),
+            Err(CommandError::Failed(..))
+        ));
+        assert!(matches!(
+            run_command(&mut Command::new("wallfacer-missing-command"), "missing"),
+            Err(CommandError::Spawn(..))
+        ));
+    }
+}
@@ -1,6 +1,16 @@
 use std::path::PathBuf;
 
-use super::aspect_ratio::AspectRatio;
+use super::{
+    aspect_ratio::{AspectRatio, AspectRatioError, parse_dimensions},
+    crop_strategy::CropConfig,
+    detector::DetectorConfig,
+    geometry::Geometry,
+    keybindings::Keybindings,
+    layout::SpanLayout,
+    metadata::MetadataConfig,
+    optimizer::OptimizerConfig,
+    upscaler::UpscalerConfig,
+};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use toml;
@@ -9,17 +19,119 @@ use toml;
 pub enum ConfigError {
     #[error("No config file found")]
     NotFound,
-    #[error("Invalid config")]
-    InvalidConfig,
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
 }
 
 pub type Result<T> = std::result::Result<T, ConfigError>;
 
+#[derive(Error, Debug)]
+pub enum ResolutionError {
+    #[error("Invalid resolution: {0}")]
+    Resolution(#[from] AspectRatioError),
+    #[error("Resolution {0} ({1}) needs both the width and height of the monitor in pixels")]
+    MissingDimensions(String, String),
+    #[error("Resolution {0} has a width and height that don't match the aspect ratio {1}")]
+    MismatchedDimensions(String, String),
+}
+
+/// resolution as it is written in the config file, older configs only stored the aspect ratio
+/// so the monitor dimensions are written separately
+#[derive(Serialize, Deserialize)]
+struct RawConfigResolution {
+    name: String,
+    description: Option<String>,
+    resolution: String,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawConfigResolution", into = "RawConfigResolution")]
 pub struct ConfigResolution {
     pub name: String,
     pub description: Option<String>,
+    /// reduced aspect ratio, used as the key for the crop metadata
     pub resolution: AspectRatio,
+    /// actual width of the monitor in pixels
+    pub width: u32,
+    /// actual height of the monitor in pixels
+    pub height: u32,
+}
+
+impl TryFrom<RawConfigResolution> for ConfigResolution {
+    type Error = ResolutionError;
+
+    fn try_from(raw: RawConfigResolution) -> std::result::Result<Self, Self::Error> {
+        let (width, height) = match (raw.width, raw.height) {
+            (Some(width), Some(height)) => {
+                if AspectRatio::try_from(raw.resolution.as_str())?
+                    != AspectRatio::new(width, height)
+                {
+                    return Err(ResolutionError::MismatchedDimensions(
+                        raw.name,
+                        raw.resolution,
+                    ));
+                }
+                (width, height)
+            }
+            // older versions saved the reduced aspect ratio, e.g. 16x9, which is kept as is until
+            // the config fills in the monitor dimensions
+            (None, None) => parse_dimensions(&raw.resolution)?,
+            _ => return Err(ResolutionError::MissingDimensions(raw.name, raw.resolution)),
+        };
+
+        Ok(Self::new(raw.name, raw.description, width, height))
+    }
+}
+
+impl From<ConfigResolution> for RawConfigResolution {
+    fn from(res: ConfigResolution) -> Self {
+        Self {
+            name: res.name,
+            description: res.description,
+            resolution: res.resolution.to_string(),
+            width: Some(res.width),
+            height: Some(res.height),
+        }
+    }
+}
+
+impl ConfigResolution {
+    pub const fn new(name: String, description: Option<String>, width: u32, height: u32) -> Self {
+        Self {
+            name,
+            description,
+            resolution: AspectRatio::new(width, height),
+            width,
+            height,
+        }
+    }
+
+    /// only the reduced aspect ratio is known, as saved by older versions
+    pub const fn is_legacy(&self) -> bool {
+        self.resolution.w == self.width && self.resolution.h == self.height
+    }
+
+    /// scale needed for the crop to fill the monitor, if the crop is smaller than the monitor
+    pub fn upscale_needed(&self, geom: &Geometry) -> Option<f64> {
+        let scale = (f64::from(self.width) / f64::from(geom.w))
+            .max(f64::from(self.height) / f64::from(geom.h));
+
+        (scale > 1.0).then_some(scale)
+    }
+}
+
+/// what the gui does with unsaved crop changes when leaving a wallpaper
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnsavedPolicy {
+    /// asks whether to save or discard, except when the window is closed by the window manager,
+    /// which can't wait for an answer, so the changes are saved
+    #[default]
+    Prompt,
+    AutoSave,
+    Discard,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -29,7 +141,17 @@ pub struct Config {
     pub min_height: u32,
     pub show_faces: bool,
     pub resolutions: Vec<ConfigResolution>,
+    /// multi-monitor layouts that a single wallpaper spans across
+    pub layouts: Vec<SpanLayout>,
     pub wallpaper_command: Option<String>,
+    pub crop: CropConfig,
+    pub metadata: MetadataConfig,
+    pub detector: DetectorConfig,
+    pub upscaler: UpscalerConfig,
+    pub optimizer: OptimizerConfig,
+    pub unsaved_changes: UnsavedPolicy,
+    /// overrides for the default editor shortcuts
+    pub keybindings: Keybindings,
 }
 
 impl Default for Config {
@@ -38,34 +160,97 @@ impl Default for Config {
             min_width: 1920,
             min_height: 1080,
             show_faces: false,
-            resolutions: vec![ConfigResolution {
-                name: "HD".into(),
-                description: Some("Full HD (1920x1080)".into()),
-                resolution: AspectRatio::new(1920, 1080),
-            }],
+            resolutions: vec![ConfigResolution::new(
+                "HD".into(),
+                Some("Full HD (1920x1080)".into()),
+                1920,
+                1080,
+            )],
+            layouts: Vec::new(),
             wallpaper_command: None,
+            crop: CropConfig::default(),
+            metadata: MetadataConfig::default(),
+            detector: DetectorConfig::default(),
+            upscaler: UpscalerConfig::default(),
+            optimizer: OptimizerConfig::default(),
+            unsaved_changes: UnsavedPolicy::default(),
+            keybindings: Keybindings::default(),
         }
     }
 }
 
 impl Config {
-    pub fn new(config_path: Option<PathBuf>) -> Result<Self> {
-        let cfg_file = config_path.unwrap_or_else(|| {
+    /// the given config file, or the default one in the xdg config directory
+    fn config_file(config_path: Option<PathBuf>) -> PathBuf {
+        config_path.unwrap_or_else(|| {
             dirs::config_dir()
                 .expect("could not get xdg config directory")
                 .join("wallfacer/wallfacer.toml")
-        });
+        })
+    }
+
+    pub fn new(config_path: Option<PathBuf>) -> Result<Self> {
+        let cfg_file = Self::config_file(config_path);
 
         if !cfg_file.exists() {
             return Ok(Self::default());
         }
 
         let contents = std::fs::read_to_string(cfg_file).map_err(|_| ConfigError::NotFound)?;
-        let mut cfg: Self = toml::from_str(&contents).map_err(|_| ConfigError::InvalidConfig)?;
+        Self::from_toml(&contents)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        let mut cfg: Self =
+            toml::from_str(contents).map_err(|e| ConfigError::InvalidConfig(e.to_string()))?;
+        cfg.fill_legacy_dimensions();
+        for option in cfg.optimizer.unsupported_options() {
+            eprintln!(
+                "Optimizer option {option} is not supported by the {} backend and is ignored.",
+                cfg.optimizer.backend
+            );
+        }
         cfg.resolutions.sort_by_key(|res| res.resolution.clone());
         Ok(cfg)
     }
 
+    /// resolutions from older configs only have the aspect ratio, so they are assumed to be the
+    /// smallest monitor with that ratio meeting the minimum dimensions, the dimensions are written
+    /// to the config on the next save
+    fn fill_legacy_dimensions(&mut self) {
+        for i in 0..self.resolutions.len() {
+            let res = &self.resolutions[i];
+            if !res.is_legacy() {
+                continue;
+            }
+
+            let (min_width, min_height) = self.min_dimensions(res.width, res.height);
+            let scale = min_width
+                .div_ceil(res.width)
+                .max(min_height.div_ceil(res.height))
+                .max(1);
+            let (width, height) = (res.width * scale, res.height * scale);
+
+            eprintln!(
+                "Resolution {} only has the aspect ratio {}, which is deprecated. Assuming {width}x{height}, set its width and height in the config.",
+                res.name, res.resolution
+            );
+
+            self.resolutions[i] =
+                ConfigResolution::new(res.name.clone(), res.description.clone(), width, height);
+        }
+    }
+
+    /// minimum dimensions for an image, rotated if the image is oriented differently from the
+    /// configured minimum so portrait images are held to the same standard
+    pub const fn min_dimensions(&self, width: u32, height: u32) -> (u32, u32) {
+        if (height > width) == (self.min_height > self.min_width) {
+            (self.min_width, self.min_height)
+        } else {
+            (self.min_height, self.min_width)
+        }
+    }
+
     pub fn sorted_resolutions(&self) -> Vec<AspectRatio> {
         self.resolutions
             .iter()
@@ -73,14 +258,100 @@ impl Config {
             .collect()
     }
 
-    /// saves the current configuration
-    pub fn save(&self) -> std::io::Result<()> {
-        let cfg_file = dirs::config_dir()
-            .expect("could not get xdg config directory")
-            .join("wallfacer/wallfacer.toml");
+    /// saves the current configuration to the file it was loaded from
+    pub fn save(&self, config_path: Option<PathBuf>) -> std::io::Result<()> {
+        let cfg_file = Self::config_file(config_path);
 
         let toml = toml::to_string(self).expect("could not serialize config");
 
         std::fs::write(cfg_file, toml)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolution_dimensions() {
+        let cfg: Config = toml::from_str(
+            r#"
+            [[resolutions]]
+            name = "HD"
+            resolution = "1920x1080"
+
+            [[resolutions]]
+            name = "Ultrawide"
+            resolution = "64x27"
+            width = 2560
+            height = 1080
+            "#,
+        )
+        .expect("invalid config");
+
+        assert_eq!(cfg.resolutions[0].resolution, AspectRatio::new(16, 9));
+        assert_eq!(
+            (cfg.resolutions[0].width, cfg.resolutions[0].height),
+            (1920, 1080)
+        );
+        assert_eq!(
+            (cfg.resolutions[1].width, cfg.resolutions[1].height),
+            (2560, 1080)
+        );
+
+        // saved configs can be read back
+        let saved = toml::to_string(&cfg).expect("could not serialize config");
+        assert_eq!(
+            toml::from_str::<Config>(&saved).expect("invalid config"),
+            cfg
+        );
+    }
+
+    #[test]
+    fn test_legacy_resolution() {
+        // older versions of `wallfacer resolution` saved the reduced aspect ratio
+        let legacy = Config::from_toml(
+            r#"
+            min_width = 1920
+            min_height = 1080
+            show_faces = false
+
+            [[resolutions]]
+            name = "HD"
+            description = "Full HD (1920x1080)"
+            resolution = "16x9"
+
+            [[resolutions]]
+            name = "Vertical"
+            resolution = "9x16"
+            "#,
+        )
+        .expect("legacy resolutions should still load");
+
+        // the smallest monitors meeting the minimum dimensions
+        let dimensions = legacy
+            .resolutions
+            .iter()
+            .map(|res| (res.name.as_str(), res.width, res.height))
+            .collect::<Vec<_>>();
+        assert!(dimensions.contains(&("HD", 1920, 1080)));
+        assert!(dimensions.contains(&("Vertical", 1080, 1920)));
+
+        // the dimensions are written back on save
+        let saved = toml::to_string(&legacy).expect("could not serialize config");
+        let reloaded = Config::from_toml(&saved).expect("could not reload config");
+        assert_eq!(reloaded.resolutions, legacy.resolutions);
+        assert!(saved.contains("width = 1920"), "{saved}");
+
+        let mismatched = toml::from_str::<Config>(
+            r#"
+            [[resolutions]]
+            name = "HD"
+            resolution = "16x10"
+            width = 1920
+            height = 1080
+            "#,
+        );
+        assert!(mismatched.is_err());
+    }
+}
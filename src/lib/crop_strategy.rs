@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    aspect_ratio::AspectRatio,
+    cropper::{Cropper, Direction},
+    geometry::Geometry,
+};
+
+pub trait CropStrategy {
+    /// the default crop for the aspect ratio
+    fn crop(&self, cropper: &Cropper, aspect_ratio: &AspectRatio) -> Geometry;
+}
+
+fn center_x(face: &Geometry) -> f64 {
+    f64::from(face.x) + f64::from(face.w) / 2.0
+}
+
+fn center_y(face: &Geometry) -> f64 {
+    f64::from(face.y) + f64::from(face.h) / 2.0
+}
+
+/// fits as many whole faces as possible, falling back to the largest face
+pub struct MostFaces;
+
+impl CropStrategy for MostFaces {
+    fn crop(&self, cropper: &Cropper, aspect_ratio: &AspectRatio) -> Geometry {
+        cropper.crop_most_faces(aspect_ratio)
+    }
+}
+
+/// places the largest face on the nearest third of the crop, or the upper third when the crop
+/// moves vertically
+pub struct RuleOfThirds;
+
+impl CropStrategy for RuleOfThirds {
+    fn crop(&self, cropper: &Cropper, aspect_ratio: &AspectRatio) -> Geometry {
+        let (target_width, target_height, direction) = cropper.crop_rect(aspect_ratio);
+        let Some(face) = cropper.faces.iter().max_by_key(|face| face.area()) else {
+            return cropper.crop_no_faces(aspect_ratio);
+        };
+
+        let target = match direction {
+            Direction::X => {
+                let (cx, w) = (center_x(face), f64::from(target_width));
+                // faces on the left of the image go on the left third
+                if cx < f64::from(cropper.width) / 2.0 {
+                    cx - w / 3.0
+                } else {
+                    cx - w * 2.0 / 3.0
+                }
+            }
+            Direction::Y => center_y(face) - f64::from(target_height) / 3.0,
+        };
+
+        cropper.clamp(target, direction, target_width, target_height)
+    }
+}
+
+/// centers the crop on the centroid of all faces, weighted by their area
+pub struct WeightedCentroid;
+
+impl CropStrategy for WeightedCentroid {
+    fn crop(&self, cropper: &Cropper, aspect_ratio: &AspectRatio) -> Geometry {
+        let (target_width, target_height, direction) = cropper.crop_rect(aspect_ratio);
+        let total_area: f64 = cropper.faces.iter().map(|f| f64::from(f.area())).sum();
+        if total_area == 0.0 {
+            return cropper.crop_no_faces(aspect_ratio);
+        }
+
+        let centroid = |center: fn(&Geometry) -> f64| {
+            cropper
+                .faces
+                .iter()
+                .map(|face| center(face) * f64::from(face.area()))
+                .sum::<f64>()
+                / total_area
+        };
+
+        let target = match direction {
+            Direction::X => centroid(center_x) - f64::from(target_width) / 2.0,
+            Direction::Y => centroid(center_y) - f64::from(target_height) / 2.0,
+        };
+
+        cropper.clamp(target, direction, target_width, target_height)
+    }
+}
+
+/// centers the crop on all the faces if they fit, otherwise on the image
+pub struct AllFacesOrCenter;
+
+impl CropStrategy for AllFacesOrCenter {
+    fn crop(&self, cropper: &Cropper, aspect_ratio: &AspectRatio) -> Geometry {
+        let (target_width, target_height, direction) = cropper.crop_rect(aspect_ratio);
+        let target = match direction {
+            Direction::X => target_width,
+            Direction::Y => target_height,
+        };
+
+        let bounds = cropper
+            .faces
+            .iter()
+            .map(|face| face.direction_bounds(direction));
+        let (Some(min), Some(max)) = (
+            bounds.clone().map(|(min, _)| min).min(),
+            bounds.map(|(_, max)| max).max(),
+        ) else {
+            return cropper.crop_no_faces(aspect_ratio);
+        };
+
+        if max - min > target {
+            return cropper.crop_center(aspect_ratio);
+        }
+
+        cropper.clamp(
+            (f64::from(min + max) - f64::from(target)) / 2.0,
+            direction,
+            target_width,
+            target_height,
+        )
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CropStrategyKind {
+    /// images without faces are cropped by their edge energy by every strategy, so the former
+    /// edge-energy strategy is the same as the default
+    #[default]
+    #[serde(alias = "edge-energy")]
+    MostFaces,
+    RuleOfThirds,
+    WeightedCentroid,
+    AllFacesOrCenter,
+}
+
+impl CropStrategyKind {
+    pub const ALL: [Self; 4] = [
+        Self::MostFaces,
+        Self::RuleOfThirds,
+        Self::WeightedCentroid,
+        Self::AllFacesOrCenter,
+    ];
+
+    /// short name shown in the editor
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::MostFaces => "Faces",
+            Self::RuleOfThirds => "Thirds",
+            Self::WeightedCentroid => "Centroid",
+            Self::AllFacesOrCenter => "All",
+        }
+    }
+}
+
+impl CropStrategy for CropStrategyKind {
+    fn crop(&self, cropper: &Cropper, aspect_ratio: &AspectRatio) -> Geometry {
+        match self {
+            Self::MostFaces => MostFaces.crop(cropper, aspect_ratio),
+            Self::RuleOfThirds => RuleOfThirds.crop(cropper, aspect_ratio),
+            Self::WeightedCentroid => WeightedCentroid.crop(cropper, aspect_ratio),
+            Self::AllFacesOrCenter => AllFacesOrCenter.crop(cropper, aspect_ratio),
+        }
+    }
+}
+
+/// selects how the default crops are positioned
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CropConfig {
+    pub strategy: CropStrategyKind,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::energy::EnergyProfile;
+
+    const SQUARE: AspectRatio = AspectRatio { w: 1, h: 1 };
+    const WIDE: AspectRatio = AspectRatio { w: 4, h: 1 };
+
+    /// square face of the given size, centered on the point
+    const fn face(cx: u32, cy: u32, size: u32) -> Geometry {
+        Geometry {
+            w: size,
+            h: size,
+            x: cx - size / 2,
+            y: cy - size / 2,
+        }
+    }
+
+    /// 1000x500 image, square crops move horizontally and 4:1 crops move vertically
+    fn cropper(faces: &[Geometry], strategy: CropStrategyKind) -> Cropper {
+        Cropper::new(faces, 1000, 500).with_strategy(strategy)
+    }
+
+    #[test]
+    fn test_most_faces() {
+        let faces = [
+            face(150, 250, 100),
+            face(350, 250, 100),
+            face(850, 250, 100),
+        ];
+        let geom = cropper(&faces, CropStrategyKind::MostFaces).crop(&SQUARE);
+
+        // both faces on the left fit within the crop
+        assert!(geom.x <= 100 && geom.xmax() >= 400, "{geom}");
+    }
+
+    #[test]
+    fn test_rule_of_thirds() {
+        let left = cropper(&[face(300, 250, 60)], CropStrategyKind::RuleOfThirds);
+        let right = cropper(&[face(700, 250, 60)], CropStrategyKind::RuleOfThirds);
+
+        // face on the left third / right third of the 500px crop
+        assert_eq!(left.crop(&SQUARE).x, 133);
+        assert_eq!(right.crop(&SQUARE).x, 366);
+        // face on the upper third of the 250px crop
+        assert_eq!(left.crop(&WIDE).y, 166);
+    }
+
+    #[test]
+    fn test_weighted_centroid() {
+        let faces = [face(200, 250, 10), face(800, 250, 30)];
+        let geom = cropper(&faces, CropStrategyKind::WeightedCentroid).crop(&SQUARE);
+
+        // (200 * 100 + 800 * 900) / 1000 = 740
+        assert_eq!(geom.x, 740 - 250);
+    }
+
+    #[test]
+    fn test_all_faces_or_center() {
+        let close = [face(600, 250, 100), face(800, 250, 100)];
+        let apart = [face(100, 250, 100), face(900, 250, 100)];
+
+        let geom = cropper(&close, CropStrategyKind::AllFacesOrCenter).crop(&SQUARE);
+        assert_eq!(geom.x, 700 - 250);
+
+        // the faces don't fit, so the crop is centered
+        let geom = cropper(&apart, CropStrategyKind::AllFacesOrCenter).crop(&SQUARE);
+        assert_eq!(geom.x, 250);
+    }
+
+    #[test]
+    fn test_no_faces_centered() {
+        for strategy in CropStrategyKind::ALL {
+            assert_eq!(cropper(&[], strategy).crop(&SQUARE).x, 250, "{strategy:?}");
+        }
+    }
+
+    #[test]
+    fn test_edge_energy_alias() {
+        let cfg: CropConfig =
+            toml::from_str(r#"strategy = "edge-energy""#).expect("invalid config");
+        assert_eq!(cfg.strategy, CropStrategyKind::MostFaces);
+    }
+
+    #[test]
+    fn test_no_faces_energy() {
+        // all the detail is on the right of the image
+        let energy = EnergyProfile {
+            columns: [vec![0; 80], vec![100; 20]].concat(),
+            rows: vec![0; 50],
+        };
+
+        for strategy in CropStrategyKind::ALL {
+            let geom = cropper(&[], strategy)
+                .with_energy(energy.clone().into())
+                .crop(&SQUARE);
+            assert_eq!(geom.x, 500, "{strategy:?}");
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use super::{
+    aspect_ratio::AspectRatio,
+    cropper::{Cropper, Direction},
+    geometry::Geometry,
+};
+
+/// crop of the same size relative to the full size crop as the zoomed source, with the same center
+fn transfer_zoomed(
+    cropper: &Cropper,
+    source: &Geometry,
+    target_width: u32,
+    target_height: u32,
+) -> Geometry {
+    // the full size crop spans the image along at least one axis
+    let zoom = (f64::from(source.w) / f64::from(cropper.width))
+        .max(f64::from(source.h) / f64::from(cropper.height))
+        .min(1.0);
+    let w = ((f64::from(target_width) * zoom).round() as u32).max(1);
+    let h = ((f64::from(target_height) * zoom).round() as u32).max(1);
+
+    let center_x = f64::from(source.x) + f64::from(source.w) / 2.0;
+    let center_y = f64::from(source.y) + f64::from(source.h) / 2.0;
+
+    Geometry {
+        w,
+        h,
+        x: (center_x - f64::from(w) / 2.0).clamp(0.0, f64::from(cropper.width - w)) as u32,
+        y: (center_y - f64::from(h) / 2.0).clamp(0.0, f64::from(cropper.height - h)) as u32,
+    }
+}
+
+/// derives the crop for another aspect ratio from an edited crop
+///
+/// crops moving along the same axis keep the same center, zoomed crops also keep their zoom,
+/// otherwise the faces within the edited crop are kept inside
+pub fn transfer_crop(cropper: &Cropper, source: &Geometry, ratio: &AspectRatio) -> Geometry {
+    let (target_width, target_height, direction) = cropper.crop_rect(ratio);
+    let target = match direction {
+        Direction::X => target_width,
+        Direction::Y => target_height,
+    };
+
+    let Some(source_direction) = Direction::of_crop(cropper.width, cropper.height, source) else {
+        return transfer_zoomed(cropper, source, target_width, target_height);
+    };
+
+    if source_direction == direction {
+        let (min, max) = source.direction_bounds(direction);
+        return cropper.clamp(
+            (f64::from(min + max) - f64::from(target)) / 2.0,
+            direction,
+            target_width,
+            target_height,
+        );
+    }
+
+    let faces = cropper
+        .faces
+        .iter()
+        .filter(|face| {
+            face.x >= source.x
+                && face.xmax() <= source.xmax()
+                && face.y >= source.y
+                && face.ymax() <= source.ymax()
+        })
+        .collect::<Vec<_>>();
+
+    let bounds = faces.iter().map(|face| face.direction_bounds(direction));
+    let (Some(min), Some(max)) = (
+        bounds.clone().map(|(min, _)| min).min(),
+        bounds.map(|(_, max)| max).max(),
+    ) else {
+        // nothing to keep, the default crop is as good as any
+        return cropper.crop(ratio);
+    };
+
+    if max - min <= target {
+        return cropper.clamp(
+            (f64::from(min + max) - f64::from(target)) / 2.0,
+            direction,
+            target_width,
+            target_height,
+        );
+    }
+
+    // the faces don't fit, keep the largest one
+    let largest = faces
+        .iter()
+        .max_by_key(|face| face.area())
+        .expect("faces is empty!");
+    cropper.crop_single_face(largest, direction, target_width, target_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: AspectRatio = AspectRatio { w: 1, h: 1 };
+    const PORTRAIT: AspectRatio = AspectRatio { w: 4, h: 5 };
+    const WIDE: AspectRatio = AspectRatio { w: 4, h: 1 };
+
+    const fn square(x: u32, y: u32, size: u32) -> Geometry {
+        Geometry {
+            w: size,
+            h: size,
+            x,
+            y,
+        }
+    }
+
+    #[test]
+    fn test_same_direction() {
+        // 1000x500 image, the square and portrait crops both move horizontally
+        let cropper = Cropper::new(&[], 1000, 500);
+        let geom = transfer_crop(&cropper, &square(100, 0, 500), &PORTRAIT);
+
+        assert_eq!(
+            geom,
+            Geometry {
+                w: 400,
+                h: 500,
+                x: 150,
+                y: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_zoomed() {
+        // half the size of the full size square crop
+        let cropper = Cropper::new(&[], 1000, 500);
+        let geom = transfer_crop(&cropper, &square(600, 100, 250), &PORTRAIT);
+
+        assert_eq!(
+            geom,
+            Geometry {
+                w: 200,
+                h: 250,
+                x: 625,
+                y: 100
+            }
+        );
+
+        // kept within the image
+        let geom = transfer_crop(&cropper, &square(750, 250, 250), &WIDE);
+        assert_eq!(
+            geom,
+            Geometry {
+                w: 500,
+                h: 125,
+                x: 500,
+                y: 312
+            }
+        );
+    }
+
+    #[test]
+    fn test_different_direction() {
+        // only the face on the right is within the edited crop
+        let faces = [square(775, 275, 50), square(175, 75, 50)];
+        let cropper = Cropper::new(&faces, 1000, 500);
+        let geom = transfer_crop(&cropper, &square(500, 0, 500), &WIDE);
+
+        assert_eq!(
+            geom,
+            Geometry {
+                w: 1000,
+                h: 250,
+                x: 0,
+                y: 175
+            }
+        );
+    }
+
+    #[test]
+    fn test_different_direction_no_faces() {
+        let cropper = Cropper::new(&[square(175, 75, 50)], 1000, 500);
+        let geom = transfer_crop(&cropper, &square(500, 0, 500), &WIDE);
+
+        assert_eq!(geom, cropper.crop(&WIDE));
+        // the face is within the wide crop, so it is kept in the square crop
+        assert_eq!(transfer_crop(&cropper, &geom, &SQUARE).x, 0);
+    }
+}
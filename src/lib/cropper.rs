@@ -1,4 +1,13 @@
-use super::{aspect_ratio::AspectRatio, geometry::Geometry};
+use super::{
+    aspect_ratio::AspectRatio,
+    crop_strategy::{CropStrategy, CropStrategyKind},
+    energy::{EnergyProfile, LazyEnergy},
+    geometry::Geometry,
+};
+
+/// version of the default crops, should be bumped whenever they change so existing crops can be
+/// refreshed with `wallfacer recrop`
+pub const CROPPER_VERSION: u32 = 1;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
@@ -6,6 +15,20 @@ pub enum Direction {
     Y,
 }
 
+impl Direction {
+    /// axis a crop of an image of the size can be moved along, none if the crop is zoomed in and
+    /// can move along both
+    pub const fn of_crop(width: u32, height: u32, geom: &Geometry) -> Option<Self> {
+        if height == geom.h {
+            Some(Self::X)
+        } else if width == geom.w {
+            Some(Self::Y)
+        } else {
+            None
+        }
+    }
+}
+
 impl std::fmt::Display for Direction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", if self == &Self::X { "X" } else { "Y" })
@@ -22,6 +45,10 @@ pub struct Cropper {
     pub faces: Vec<Geometry>,
     pub width: u32,
     pub height: u32,
+    /// how the default crops are positioned
+    pub strategy: CropStrategyKind,
+    /// detail of the image, used to crop images without faces, which are centered without it
+    pub energy: LazyEnergy,
 }
 
 fn sort_faces_by_direction(faces: Vec<Geometry>, direction: Direction) -> Vec<Geometry> {
@@ -39,9 +66,23 @@ impl Cropper {
             faces: faces.to_vec(),
             width,
             height,
+            strategy: CropStrategyKind::default(),
+            energy: LazyEnergy::default(),
         }
     }
 
+    #[must_use]
+    pub const fn with_strategy(mut self, strategy: CropStrategyKind) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    #[must_use]
+    pub fn with_energy(mut self, energy: LazyEnergy) -> Self {
+        self.energy = energy;
+        self
+    }
+
     pub fn crop_rect(&self, aspect_ratio: &AspectRatio) -> (u32, u32, Direction) {
         use std::cmp::min;
         let AspectRatio {
@@ -102,13 +143,52 @@ impl Cropper {
         let target = match direction {
             Direction::X => (f64::from(face.x + face.xmax()) - f64::from(target_width)) / 2.0,
             Direction::Y => {
-                // top of face - 15% of image height
-                f64::from(self.height).mul_add(-0.15, f64::from(face.y))
+                // top of face - 15% of the shorter side, portrait images would leave too much
+                // headroom if the height was used
+                f64::from(self.width.min(self.height)).mul_add(-0.15, f64::from(face.y))
             }
         };
         self.clamp(target, direction, target_width, target_height)
     }
 
+    /// crop at the midpoint of the image
+    pub fn crop_center(&self, aspect_ratio: &AspectRatio) -> Geometry {
+        let (target_width, target_height, direction) = self.crop_rect(aspect_ratio);
+        let (x, y) = match direction {
+            Direction::X => ((self.width - target_width) / 2, 0),
+            Direction::Y => (0, (self.height - target_height) / 2),
+        };
+
+        Geometry {
+            x,
+            y,
+            w: target_width,
+            h: target_height,
+        }
+    }
+
+    /// crop with the most edges along the direction, centered if the energy is unknown
+    fn crop_energy(&self, direction: Direction, target_width: u32, target_height: u32) -> Geometry {
+        let start = match (self.energy.get(), direction) {
+            (Some(energy), Direction::X) => {
+                EnergyProfile::best_window(&energy.columns, self.width, target_width)
+            }
+            (Some(energy), Direction::Y) => {
+                EnergyProfile::best_window(&energy.rows, self.height, target_height)
+            }
+            (None, Direction::X) => (self.width - target_width) / 2,
+            (None, Direction::Y) => (self.height - target_height) / 2,
+        };
+
+        self.clamp(f64::from(start), direction, target_width, target_height)
+    }
+
+    /// crop for images without faces, keeping the area with the most detail
+    pub fn crop_no_faces(&self, aspect_ratio: &AspectRatio) -> Geometry {
+        let (target_width, target_height, direction) = self.crop_rect(aspect_ratio);
+        self.crop_energy(direction, target_width, target_height)
+    }
+
     /// trivial crops, either same aspect ratio (entire image), no faces or single face
     fn crop_trivial(
         &self,
@@ -126,18 +206,9 @@ impl Cropper {
             });
         }
 
-        // midpoint of image
+        // area with the most detail, or the midpoint of image
         if self.faces.is_empty() {
-            let (x, y) = match direction {
-                Direction::X => ((self.width - target_width) / 2, 0),
-                Direction::Y => (0, (self.height - target_height) / 2),
-            };
-            return Some(Geometry {
-                x,
-                y,
-                w: target_width,
-                h: target_height,
-            });
+            return Some(self.crop_energy(direction, target_width, target_height));
         }
 
         if self.faces.len() == 1 {
@@ -182,7 +253,13 @@ impl Cropper {
         (start..=end).map(move |rect_start| (rect_start, rect_start + target))
     }
 
+    /// the default crop using the selected strategy
     pub fn crop(&self, aspect_ratio: &AspectRatio) -> Geometry {
+        self.strategy.crop(self, aspect_ratio)
+    }
+
+    /// fits as many faces as possible within the crop
+    pub fn crop_most_faces(&self, aspect_ratio: &AspectRatio) -> Geometry {
         let (target_width, target_height, direction) = self.crop_rect(aspect_ratio);
         let target = match direction {
             Direction::X => target_width,
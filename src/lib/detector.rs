@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use thiserror::Error;
+
+use super::{
+    Bbox,
+    command::{CommandError, run_command},
+    filename, full_path,
+    geometry::Geometry,
+};
+
+/// placeholder in the command args that is replaced with the image path
+const INPUT_PLACEHOLDER: &str = "{input}";
+
+#[derive(Error, Debug)]
+pub enum DetectorError {
+    #[error(transparent)]
+    Command(#[from] CommandError),
+    #[error("Unable to parse detected faces: {0}")]
+    Parse(String),
+    #[error("Unable to read fixture {0}: {1}")]
+    Fixture(PathBuf, String),
+    #[error("No command configured for the command detector")]
+    MissingCommand,
+    #[error("No fixture configured for the fixture detector")]
+    MissingFixture,
+}
+
+pub trait FaceDetector {
+    /// detects the faces within the image, in image coordinates
+    fn detect(&self, img: &Path) -> Result<Vec<Geometry>, DetectorError>;
+}
+
+/// how each face is written in the output of the detector
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FaceFormat {
+    /// objects with xmin, ymin, xmax and ymax
+    #[default]
+    Bbox,
+    /// objects with x, y, w and h or arrays of [x, y, w, h]
+    Xywh,
+    /// strings in the form of WxH+X+Y
+    Geometry,
+}
+
+#[derive(Deserialize)]
+struct Xywh {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+fn parse_face(value: &Value, format: FaceFormat) -> Result<Geometry, DetectorError> {
+    let invalid = |e: &dyn std::fmt::Display| DetectorError::Parse(format!("{value}: {e}"));
+
+    match format {
+        FaceFormat::Bbox => serde_json::from_value::<Bbox>(value.clone())
+            .map(|bbox| bbox.to_face())
+            .map_err(|e| invalid(&e)),
+        FaceFormat::Xywh => serde_json::from_value::<Xywh>(value.clone())
+            .or_else(|_| {
+                serde_json::from_value::<[u32; 4]>(value.clone()).map(|[x, y, w, h]| Xywh {
+                    x,
+                    y,
+                    w,
+                    h,
+                })
+            })
+            .map(|Xywh { x, y, w, h }| Geometry { w, h, x, y })
+            .map_err(|e| invalid(&e)),
+        FaceFormat::Geometry => value
+            .as_str()
+            .ok_or_else(|| invalid(&"expected a string"))
+            .and_then(|s| Geometry::try_from(s).map_err(|e| invalid(&e))),
+    }
+}
+
+/// parses the faces from the json output of a detector
+///
+/// if no pointer is given the output should either be the list of faces or an object with the
+/// list as its first value, as only a single image is passed at a time
+pub fn parse_faces(
+    output: &str,
+    format: FaceFormat,
+    pointer: Option<&str>,
+) -> Result<Vec<Geometry>, DetectorError> {
+    let output: Value =
+        serde_json::from_str(output.trim()).map_err(|e| DetectorError::Parse(e.to_string()))?;
+
+    let faces = pointer.map_or_else(
+        || match &output {
+            Value::Object(obj) => obj.values().next(),
+            _ => Some(&output),
+        },
+        |pointer| output.pointer(pointer),
+    );
+
+    let faces = faces
+        .and_then(Value::as_array)
+        .ok_or_else(|| DetectorError::Parse(format!("no list of faces found in {output}")))?;
+
+    faces.iter().map(|face| parse_face(face, format)).collect()
+}
+
+/// runs the command and returns its stdout
+fn run_detector(cmd: &mut Command, name: &str) -> Result<String, DetectorError> {
+    let stdout = run_command(cmd, name)?;
+    String::from_utf8(stdout).map_err(|e| DetectorError::Parse(e.to_string()))
+}
+
+/// <https://github.com/hysts/anime-face-detector>
+pub struct AnimeFaceDetector;
+
+impl FaceDetector for AnimeFaceDetector {
+    fn detect(&self, img: &Path) -> Result<Vec<Geometry>, DetectorError> {
+        let output = run_detector(
+            Command::new("anime-face-detector").arg(img),
+            "anime-face-detector",
+        )?;
+
+        parse_faces(&output, FaceFormat::Bbox, None)
+    }
+}
+
+/// any command that outputs the faces as json
+pub struct CommandDetector {
+    pub command: String,
+    pub args: Vec<String>,
+    pub format: FaceFormat,
+    pub pointer: Option<String>,
+}
+
+impl FaceDetector for CommandDetector {
+    fn detect(&self, img: &Path) -> Result<Vec<Geometry>, DetectorError> {
+        let mut cmd = Command::new(full_path(&self.command));
+
+        if self.args.iter().any(|arg| arg.contains(INPUT_PLACEHOLDER)) {
+            let input = img.to_string_lossy();
+            cmd.args(
+                self.args
+                    .iter()
+                    .map(|arg| arg.replace(INPUT_PLACEHOLDER, &input)),
+            );
+        } else {
+            cmd.args(&self.args).arg(img);
+        }
+
+        let output = run_detector(&mut cmd, &self.command)?;
+        parse_faces(&output, self.format, self.pointer.as_deref())
+    }
+}
+
+/// never detects any faces, so the default crops are centered
+pub struct NoDetector;
+
+impl FaceDetector for NoDetector {
+    fn detect(&self, _img: &Path) -> Result<Vec<Geometry>, DetectorError> {
+        Ok(Vec::new())
+    }
+}
+
+/// reads the faces from a json file mapping filenames to faces, for testing without a model
+pub struct FixtureDetector {
+    pub path: PathBuf,
+}
+
+impl FaceDetector for FixtureDetector {
+    fn detect(&self, img: &Path) -> Result<Vec<Geometry>, DetectorError> {
+        let fixture_err =
+            |e: &dyn std::fmt::Display| DetectorError::Fixture(self.path.clone(), e.to_string());
+
+        let contents = std::fs::read_to_string(&self.path).map_err(|e| fixture_err(&e))?;
+        let mut fixture: HashMap<String, Vec<Geometry>> =
+            serde_json::from_str(&contents).map_err(|e| fixture_err(&e))?;
+
+        // images missing from the fixture have no faces
+        Ok(fixture.remove(&filename(img)).unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DetectorBackend {
+    #[default]
+    AnimeFaceDetector,
+    Command,
+    None,
+    Fixture,
+}
+
+/// selects how faces are detected when adding wallpapers
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectorConfig {
+    pub backend: DetectorBackend,
+    /// executable for the command backend
+    pub command: Option<String>,
+    /// args for the command backend, the image path replaces "{input}" or is appended
+    pub args: Vec<String>,
+    pub format: FaceFormat,
+    /// json pointer to the list of faces within the output of the command backend
+    pub faces_pointer: Option<String>,
+    /// json file for the fixture backend
+    pub fixture: Option<String>,
+    /// runs the command backend on one image at a time, for commands that need the whole gpu
+    pub exclusive: bool,
+}
+
+impl DetectorConfig {
+    /// anime-face-detector loads its model onto the gpu, so multiple instances would compete for
+    /// vram, other commands only wait for each other when configured to
+    pub const fn is_exclusive(&self) -> bool {
+        match self.backend {
+            DetectorBackend::AnimeFaceDetector => true,
+            DetectorBackend::Command => self.exclusive,
+            DetectorBackend::None | DetectorBackend::Fixture => false,
+        }
+    }
+}
+
+impl FaceDetector for DetectorConfig {
+    fn detect(&self, img: &Path) -> Result<Vec<Geometry>, DetectorError> {
+        match self.backend {
+            DetectorBackend::AnimeFaceDetector => AnimeFaceDetector.detect(img),
+            DetectorBackend::Command => CommandDetector {
+                command: self.command.clone().ok_or(DetectorError::MissingCommand)?,
+                args: self.args.clone(),
+                format: self.format,
+                pointer: self.faces_pointer.clone(),
+            }
+            .detect(img),
+            DetectorBackend::None => NoDetector.detect(img),
+            DetectorBackend::Fixture => FixtureDetector {
+                path: self
+                    .fixture
+                    .as_deref()
+                    .map(full_path)
+                    .ok_or(DetectorError::MissingFixture)?,
+            }
+            .detect(img),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+
+    const FACE: Geometry = Geometry {
+        w: 30,
+        h: 40,
+        x: 10,
+        y: 20,
+    };
+
+    #[test]
+    fn test_parse_anime_face_detector() {
+        let output =
+            r#"{"/tmp/img.png": [{"xmin": 10, "ymin": 20, "xmax": 40, "ymax": 60, "score": 0.9}]}"#;
+        let faces = parse_faces(output, FaceFormat::Bbox, None).expect("could not parse faces");
+        assert_eq!(faces, vec![FACE]);
+    }
+
+    #[test]
+    fn test_parse_xywh() {
+        let faces = parse_faces(
+            r#"{"faces": [{"x": 10, "y": 20, "w": 30, "h": 40}, [10, 20, 30, 40]]}"#,
+            FaceFormat::Xywh,
+            Some("/faces"),
+        )
+        .expect("could not parse faces");
+        assert_eq!(faces, vec![FACE, FACE]);
+    }
+
+    #[test]
+    fn test_parse_geometry() {
+        let faces = parse_faces(r#"["30x40+10+20"]"#, FaceFormat::Geometry, None)
+            .expect("could not parse faces");
+        assert_eq!(faces, vec![FACE]);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse_faces("[]", FaceFormat::Bbox, Some("/faces")).is_err());
+        assert!(parse_faces(r#"[{"x": 1}]"#, FaceFormat::Xywh, None).is_err());
+    }
+
+    #[test]
+    fn test_fixture_detector() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let fixture = workspace.dir().join("fixture.json");
+        std::fs::write(&fixture, r#"{"img.png": ["30x40+10+20"]}"#)
+            .expect("could not write fixture");
+
+        let detector = FixtureDetector { path: fixture };
+        let faces = detector.detect(Path::new("/wallpapers/img.png"));
+        let missing = detector.detect(Path::new("/wallpapers/other.png"));
+
+        assert_eq!(faces.expect("could not read fixture"), vec![FACE]);
+        assert_eq!(missing.expect("could not read fixture"), Vec::new());
+    }
+
+    #[test]
+    fn test_exclusive() {
+        let mut cfg = DetectorConfig::default();
+        assert!(cfg.is_exclusive());
+
+        // commands only run one at a time when configured to
+        cfg.backend = DetectorBackend::Command;
+        assert!(!cfg.is_exclusive());
+        cfg.exclusive = true;
+        assert!(cfg.is_exclusive());
+
+        // exclusive has no effect on backends that don't run a model
+        cfg.backend = DetectorBackend::Fixture;
+        assert!(!cfg.is_exclusive());
+    }
+}
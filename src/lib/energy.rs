@@ -0,0 +1,170 @@
+use image::{DynamicImage, ImageReader, imageops::FilterType};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+/// longest side of the downscaled image the energy is computed from
+const ENERGY_SIZE: u32 = 512;
+
+/// how much detail each column and row of an image has, based on the strength of its edges, so
+/// crops without faces can keep the interesting parts of the image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnergyProfile {
+    /// energy of each column of the downscaled image
+    pub columns: Vec<u64>,
+    /// energy of each row of the downscaled image
+    pub rows: Vec<u64>,
+}
+
+impl EnergyProfile {
+    pub fn from_path(img: &Path) -> Result<Self, image::ImageError> {
+        Ok(Self::from_image(
+            &ImageReader::open(img)?.with_guessed_format()?.decode()?,
+        ))
+    }
+
+    pub fn from_image(img: &DynamicImage) -> Self {
+        let gray = img
+            .resize(ENERGY_SIZE, ENERGY_SIZE, FilterType::Triangle)
+            .to_luma8();
+        let (w, h) = gray.dimensions();
+
+        let mut columns = vec![0; w as usize];
+        let mut rows = vec![0; h as usize];
+
+        // gradient magnitude, using the difference to the next pixel
+        for (x, y, px) in gray.enumerate_pixels() {
+            let value = i32::from(px[0]);
+            let dx = if x + 1 < w {
+                (i32::from(gray.get_pixel(x + 1, y)[0]) - value).unsigned_abs()
+            } else {
+                0
+            };
+            let dy = if y + 1 < h {
+                (i32::from(gray.get_pixel(x, y + 1)[0]) - value).unsigned_abs()
+            } else {
+                0
+            };
+
+            let energy = u64::from(dx + dy);
+            columns[x as usize] += energy;
+            rows[y as usize] += energy;
+        }
+
+        Self { columns, rows }
+    }
+
+    /// start of the window of the given length with the most energy, in image coordinates along
+    /// an axis of the given length
+    pub fn best_window(profile: &[u64], img_len: u32, window: u32) -> u32 {
+        if profile.is_empty() || window >= img_len {
+            return 0;
+        }
+
+        let scale = profile.len() as f64 / f64::from(img_len);
+        let window_len = ((f64::from(window) * scale).round() as usize).clamp(1, profile.len());
+
+        // sliding sum over the profile
+        let mut sum: u64 = profile[..window_len].iter().sum();
+        let (mut best_start, mut best_sum) = (0, sum);
+        for start in 1..=(profile.len() - window_len) {
+            sum = sum + profile[start + window_len - 1] - profile[start - 1];
+            if sum > best_sum {
+                best_start = start;
+                best_sum = sum;
+            }
+        }
+
+        ((best_start as f64 / scale).round() as u32).min(img_len - window)
+    }
+}
+
+/// energy of an image that is only computed the first time it is needed, as decoding the image
+/// is slow and most crops never need it, clones share the computed energy
+#[derive(Debug, Clone, Default)]
+pub struct LazyEnergy {
+    path: Option<PathBuf>,
+    energy: Arc<OnceLock<Option<EnergyProfile>>>,
+}
+
+impl LazyEnergy {
+    pub fn new(img: &Path) -> Self {
+        Self {
+            path: Some(img.to_path_buf()),
+            energy: Arc::default(),
+        }
+    }
+
+    /// the energy of the image, none if it is unreadable or no image was given
+    pub fn get(&self) -> Option<&EnergyProfile> {
+        self.energy
+            .get_or_init(|| {
+                self.path
+                    .as_deref()
+                    .and_then(|img| EnergyProfile::from_path(img).ok())
+            })
+            .as_ref()
+    }
+}
+
+impl From<EnergyProfile> for LazyEnergy {
+    fn from(energy: EnergyProfile) -> Self {
+        Self {
+            path: None,
+            energy: Arc::new(OnceLock::from(Some(energy))),
+        }
+    }
+}
+
+/// the energy is derived from the image, so it doesn't need to be compared
+impl PartialEq for LazyEnergy {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for LazyEnergy {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_best_window() {
+        let profile = [0, 0, 0, 10, 10, 0, 0, 0];
+
+        assert_eq!(EnergyProfile::best_window(&profile, 800, 200), 300);
+        // windows are kept within the image
+        assert_eq!(EnergyProfile::best_window(&profile, 800, 800), 0);
+        assert_eq!(EnergyProfile::best_window(&[], 800, 200), 0);
+    }
+
+    #[test]
+    fn test_from_image() {
+        // a single vertical line on the right of the image
+        let img =
+            image::GrayImage::from_fn(64, 32, |x, _| image::Luma([if x == 48 { 255 } else { 0 }]));
+        let energy = EnergyProfile::from_image(&DynamicImage::ImageLuma8(img));
+
+        let (max_col, _) = energy
+            .columns
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, e)| **e)
+            .expect("no columns");
+        assert!(max_col > energy.columns.len() / 2);
+    }
+
+    #[test]
+    fn test_lazy_energy() {
+        let energy = EnergyProfile {
+            columns: vec![1, 2],
+            rows: vec![3],
+        };
+
+        assert_eq!(LazyEnergy::from(energy.clone()).get(), Some(&energy));
+        assert_eq!(LazyEnergy::default().get(), None);
+        assert_eq!(LazyEnergy::new(Path::new("/nonexistent.png")).get(), None);
+    }
+}
@@ -1,4 +1,5 @@
 use itertools::Itertools;
+use serde::{Deserialize, Serialize, de};
 use thiserror::Error;
 
 use crate::cropper::Direction;
@@ -24,6 +25,25 @@ impl std::fmt::Display for Geometry {
     }
 }
 
+impl Serialize for Geometry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Geometry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::try_from(s.as_str()).map_err(de::Error::custom)
+    }
+}
+
 impl std::cmp::Ord for Geometry {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.x.cmp(&other.x).then_with(|| self.y.cmp(&other.y))
@@ -116,35 +136,19 @@ impl Geometry {
 
     #[must_use]
     pub fn align_center(&self, img_width: u32, img_height: u32) -> Self {
-        if img_height == self.h {
-            Self {
-                x: (img_width - self.w) / 2,
-                y: 0,
-                ..self.clone()
-            }
-        } else {
-            Self {
-                x: 0,
-                y: (img_height - self.h) / 2,
-                ..self.clone()
-            }
+        Self {
+            x: (img_width - self.w) / 2,
+            y: (img_height - self.h) / 2,
+            ..self.clone()
         }
     }
 
     #[must_use]
     pub fn align_end(&self, img_width: u32, img_height: u32) -> Self {
-        if img_height == self.h {
-            Self {
-                x: img_width - self.w,
-                y: 0,
-                ..self.clone()
-            }
-        } else {
-            Self {
-                x: 0,
-                y: img_height - self.h,
-                ..self.clone()
-            }
+        Self {
+            x: img_width - self.w,
+            y: img_height - self.h,
+            ..self.clone()
         }
     }
 
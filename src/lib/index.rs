@@ -0,0 +1,381 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::{
+    aspect_ratio::AspectRatio,
+    geometry::Geometry,
+    metadata::{CropProvenance, MetadataConfig, MetadataStore},
+    wallpapers::{WallInfo, WallInfoError},
+};
+
+/// used to detect if a wallpaper has changed since it was indexed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Fingerprint {
+    mtime: SystemTime,
+    /// the mtime is restored after writing embedded metadata and the size usually stays the same,
+    /// but the ctime can't be reset so it still changes when another process saves the wallpaper
+    ctime: (i64, i64),
+    size: u64,
+    sidecar_mtime: Option<SystemTime>,
+}
+
+impl Fingerprint {
+    fn new(img: &Path, store: &impl MetadataStore) -> Option<Self> {
+        let meta = std::fs::metadata(img).ok()?;
+
+        Some(Self {
+            mtime: meta.modified().ok()?,
+            ctime: (meta.ctime(), meta.ctime_nsec()),
+            size: meta.len(),
+            sidecar_mtime: store
+                .sidecar_path(img)
+                .and_then(|sidecar| sidecar.metadata().and_then(|m| m.modified()).ok()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    fingerprint: Fingerprint,
+    has_metadata: bool,
+    width: u32,
+    height: u32,
+    faces: Vec<Geometry>,
+    scale: Option<u32>,
+    crops: BTreeMap<AspectRatio, Geometry>,
+    #[serde(default)]
+    provenance: BTreeMap<AspectRatio, CropProvenance>,
+    #[serde(default)]
+    spans: BTreeMap<String, Geometry>,
+}
+
+impl IndexEntry {
+    fn to_info(&self, path: &Path) -> WallInfo {
+        WallInfo {
+            path: path.to_path_buf(),
+            width: self.width,
+            height: self.height,
+            faces: self.faces.clone(),
+            scale: self.scale,
+            geometries: self.crops.clone().into_iter().collect(),
+            provenance: self.provenance.clone().into_iter().collect(),
+            spans: self.spans.clone().into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct IndexFile {
+    /// the index is only valid for the backend it was built with
+    metadata: MetadataConfig,
+    entries: HashMap<PathBuf, IndexEntry>,
+}
+
+/// borrowed version of `IndexFile` to avoid cloning the entire index when saving
+#[derive(Serialize)]
+struct IndexFileRef<'a> {
+    metadata: &'a MetadataConfig,
+    entries: &'a HashMap<PathBuf, IndexEntry>,
+}
+
+/// cache of the wallfacer data for each wallpaper, stored in the xdg cache directory
+#[derive(Debug)]
+pub struct WallIndex {
+    store: MetadataConfig,
+    entries: HashMap<PathBuf, IndexEntry>,
+    is_dirty: bool,
+}
+
+impl WallIndex {
+    fn index_file() -> PathBuf {
+        dirs::cache_dir()
+            .expect("could not get xdg cache directory")
+            .join("wallfacer/index.json")
+    }
+
+    /// paths are canonicalized so the same wallpaper is only indexed once
+    fn key(path: &Path) -> PathBuf {
+        std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// creates an empty index
+    pub fn new(store: &MetadataConfig) -> Self {
+        Self {
+            store: store.clone(),
+            entries: HashMap::new(),
+            is_dirty: false,
+        }
+    }
+
+    /// loads the index, discarding it if it is unreadable or was built with a different backend
+    pub fn load(store: &MetadataConfig) -> Self {
+        Self::load_from(&Self::index_file(), store)
+    }
+
+    fn load_from(index_file: &Path, store: &MetadataConfig) -> Self {
+        let entries = std::fs::read_to_string(index_file)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<IndexFile>(&contents).ok())
+            .filter(|index| &index.metadata == store)
+            .map(|index| index.entries)
+            .unwrap_or_default();
+
+        Self {
+            entries,
+            ..Self::new(store)
+        }
+    }
+
+    /// gets the wallpaper from the index, reading it from the file if it has changed
+    pub fn get(&mut self, path: &Path) -> Result<WallInfo, WallInfoError> {
+        let key = Self::key(path);
+        let fingerprint = Fingerprint::new(path, &self.store);
+
+        if let Some(entry) = self.entries.get(&key)
+            && Some(&entry.fingerprint) == fingerprint.as_ref()
+        {
+            return Ok(entry.to_info(path));
+        }
+
+        let info = WallInfo::new_from_file(path, &self.store)?;
+        self.insert(&info);
+        Ok(info)
+    }
+
+    pub fn has_metadata(&mut self, path: &Path) -> bool {
+        // refreshes the entry if the wallpaper has changed
+        if self.get(path).is_err() {
+            return false;
+        }
+
+        self.entries
+            .get(&Self::key(path))
+            .is_some_and(|entry| entry.has_metadata)
+    }
+
+    /// saves the wallpaper using the metadata backend and updates the index
+    pub fn save_info(&mut self, info: &WallInfo) -> Result<(), WallInfoError> {
+        info.save(&self.store)?;
+        self.insert(info);
+        Ok(())
+    }
+
+    /// updates the index after the wallpaper has been saved
+    pub fn insert(&mut self, info: &WallInfo) {
+        let Some(fingerprint) = Fingerprint::new(&info.path, &self.store) else {
+            return;
+        };
+
+        self.entries.insert(
+            Self::key(&info.path),
+            IndexEntry {
+                fingerprint,
+                has_metadata: self.store.has_metadata(&info.path),
+                width: info.width,
+                height: info.height,
+                faces: info.faces.clone(),
+                scale: info.scale,
+                crops: info.geometries.clone().into_iter().collect(),
+                provenance: info.provenance.clone().into_iter().collect(),
+                spans: info.spans.clone().into_iter().collect(),
+            },
+        );
+        self.is_dirty = true;
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        if self.entries.remove(&Self::key(path)).is_some() {
+            self.is_dirty = true;
+        }
+    }
+
+    /// removes entries for wallpapers that no longer exist
+    pub fn prune(&mut self) {
+        let prev_len = self.entries.len();
+        self.entries.retain(|path, _| path.exists());
+        self.is_dirty |= self.entries.len() != prev_len;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// writes the index to disk if it has been modified
+    pub fn save(&mut self) -> std::io::Result<()> {
+        self.save_to(&Self::index_file())
+    }
+
+    fn save_to(&mut self, index_file: &Path) -> std::io::Result<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+
+        if let Some(parent) = index_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let contents = serde_json::to_string(&IndexFileRef {
+            metadata: &self.store,
+            entries: &self.entries,
+        })
+        .map_err(std::io::Error::other)?;
+
+        std::fs::write(index_file, contents)?;
+        self.is_dirty = false;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metadata::MetadataBackend, workspace::TempWorkspace};
+    use image::RgbImage;
+    use std::{fs::File, io::Write, os::unix::fs::PermissionsExt, time::Duration};
+
+    const FACE: Geometry = Geometry {
+        x: 1,
+        y: 1,
+        w: 2,
+        h: 2,
+    };
+
+    fn sidecar_store() -> MetadataConfig {
+        MetadataConfig {
+            backend: MetadataBackend::Sidecar,
+            sidecar_dir: None,
+        }
+    }
+
+    fn write_image(workspace: &TempWorkspace) -> PathBuf {
+        let img = workspace.dir().join("img.png");
+        RgbImage::new(8, 4)
+            .save(&img)
+            .expect("could not write image");
+        img
+    }
+
+    fn set_mtime(path: &Path, mtime: SystemTime) {
+        File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(mtime))
+            .expect("could not set mtime");
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let store = sidecar_store();
+        let img = write_image(&workspace);
+        let fingerprint = || Fingerprint::new(&img, &store).expect("could not read fingerprint");
+
+        let before = fingerprint();
+        set_mtime(&img, SystemTime::UNIX_EPOCH);
+        let after = fingerprint();
+        assert_ne!(after.mtime, before.mtime);
+
+        // changing the file without its mtime, e.g. writing embedded metadata, still changes the
+        // ctime, which is coarser than a nanosecond on some filesystems
+        let before = after;
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::set_permissions(&img, std::fs::Permissions::from_mode(0o600))
+            .expect("could not set permissions");
+        let after = fingerprint();
+        assert_eq!((&after.mtime, after.size), (&before.mtime, before.size));
+        assert_ne!(after.ctime, before.ctime);
+
+        let before = after;
+        File::options()
+            .append(true)
+            .open(&img)
+            .and_then(|mut file| file.write_all(b"extra"))
+            .expect("could not append to image");
+        set_mtime(&img, SystemTime::UNIX_EPOCH);
+        let after = fingerprint();
+        assert_eq!(after.mtime, before.mtime);
+        assert_eq!(after.size, before.size + 5);
+
+        // the sidecar is edited separately from the image
+        let sidecar = store
+            .sidecar_path(&img)
+            .expect("sidecar store has sidecars");
+        assert_eq!(after.sidecar_mtime, None);
+        std::fs::write(&sidecar, "{}").expect("could not write sidecar");
+        set_mtime(&sidecar, SystemTime::UNIX_EPOCH);
+        let before = fingerprint();
+        assert_eq!(before.sidecar_mtime, Some(SystemTime::UNIX_EPOCH));
+        set_mtime(&sidecar, SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+        assert_ne!(fingerprint().sidecar_mtime, before.sidecar_mtime);
+    }
+
+    #[test]
+    fn test_refresh() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let store = sidecar_store();
+        let img = write_image(&workspace);
+        let sidecar = store
+            .sidecar_path(&img)
+            .expect("sidecar store has sidecars");
+
+        let mut index = WallIndex::new(&store);
+        assert!(!index.has_metadata(&img));
+
+        let mut info = index.get(&img).expect("could not read image");
+        info.faces = vec![FACE];
+        index.save_info(&info).expect("could not save metadata");
+        assert!(index.has_metadata(&img));
+        assert_eq!(
+            index.get(&img).expect("could not read image").faces,
+            vec![FACE]
+        );
+
+        // saved by another process, with a different mtime as it can be coarser than the writes
+        info.faces = Vec::new();
+        info.save(&store).expect("could not save metadata");
+        set_mtime(&sidecar, SystemTime::UNIX_EPOCH);
+        assert!(
+            index
+                .get(&img)
+                .expect("could not read image")
+                .faces
+                .is_empty()
+        );
+
+        std::fs::remove_file(&sidecar).expect("could not remove sidecar");
+        assert!(!index.has_metadata(&img));
+    }
+
+    #[test]
+    fn test_load_other_backend() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let index_file = workspace.dir().join("index.json");
+        let store = sidecar_store();
+        let img = write_image(&workspace);
+
+        let mut index = WallIndex::new(&store);
+        index.get(&img).expect("could not read image");
+        index.save_to(&index_file).expect("could not save index");
+        assert_eq!(WallIndex::load_from(&index_file, &store).len(), 1);
+
+        // the cached data would be stale if the metadata is read from a different location
+        let embedded = store.with_backend(MetadataBackend::Embedded);
+        assert!(WallIndex::load_from(&index_file, &embedded).is_empty());
+
+        let sidecar_dir = MetadataConfig {
+            sidecar_dir: Some("/sidecars".to_string()),
+            ..store
+        };
+        assert!(WallIndex::load_from(&index_file, &sidecar_dir).is_empty());
+    }
+}
@@ -0,0 +1,538 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum KeybindingError {
+    #[error("Invalid key combination {0}")]
+    InvalidCombo(String),
+    #[error("Unknown modifier {0}, valid modifiers are ctrl, shift, alt and meta")]
+    UnknownModifier(String),
+    #[error("Unknown action {0}")]
+    UnknownAction(String),
+    #[error("{0} can't be rebound")]
+    Reserved(KeyCombo),
+    #[error("{0} is bound to both {1} and {2}")]
+    Conflict(KeyCombo, Action, Action),
+}
+
+/// keys handled by the app that can't be bound to an action
+const RESERVED_KEYS: [&str; 2] = ["Escape", "F12"];
+
+/// named keys that can be bound, single characters can always be bound
+const NAMED_KEYS: [&str; 23] = [
+    "Escape",
+    "Tab",
+    "Enter",
+    "Backspace",
+    "Delete",
+    "Insert",
+    "Home",
+    "End",
+    "PageUp",
+    "PageDown",
+    "F1",
+    "F2",
+    "F3",
+    "F4",
+    "F5",
+    "F6",
+    "F7",
+    "F8",
+    "F9",
+    "F10",
+    "F11",
+    "F12",
+    "Space",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Action {
+    ToggleFilelist,
+    Quit,
+    Save,
+    Help,
+    ToggleFaces,
+    EditFaces,
+    CompareStrategies,
+    PrevImage,
+    NextImage,
+    AlignStart,
+    AlignCenter,
+    AlignEnd,
+    SourceCrop,
+    DefaultCrop,
+    Propagate,
+    ZoomIn,
+    ZoomOut,
+    Undo,
+    Redo,
+    NextRatio,
+}
+
+impl Action {
+    pub const ALL: [Self; 20] = [
+        Self::ToggleFilelist,
+        Self::Quit,
+        Self::Save,
+        Self::Help,
+        Self::ToggleFaces,
+        Self::EditFaces,
+        Self::CompareStrategies,
+        Self::PrevImage,
+        Self::NextImage,
+        Self::AlignStart,
+        Self::AlignCenter,
+        Self::AlignEnd,
+        Self::SourceCrop,
+        Self::DefaultCrop,
+        Self::Propagate,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::Undo,
+        Self::Redo,
+        Self::NextRatio,
+    ];
+
+    /// name used in the config file
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::ToggleFilelist => "toggle-filelist",
+            Self::Quit => "quit",
+            Self::Save => "save",
+            Self::Help => "help",
+            Self::ToggleFaces => "toggle-faces",
+            Self::EditFaces => "edit-faces",
+            Self::CompareStrategies => "compare-strategies",
+            Self::PrevImage => "prev-image",
+            Self::NextImage => "next-image",
+            Self::AlignStart => "align-start",
+            Self::AlignCenter => "align-center",
+            Self::AlignEnd => "align-end",
+            Self::SourceCrop => "source-crop",
+            Self::DefaultCrop => "default-crop",
+            Self::Propagate => "propagate",
+            Self::ZoomIn => "zoom-in",
+            Self::ZoomOut => "zoom-out",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::NextRatio => "next-ratio",
+        }
+    }
+
+    /// only available while editing a wallpaper
+    pub const fn is_editor(self) -> bool {
+        !matches!(
+            self,
+            Self::ToggleFilelist | Self::Quit | Self::Save | Self::Help
+        )
+    }
+
+    /// shown in the help overlay
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::ToggleFilelist => "Toggle the file list",
+            Self::Quit => "Quit",
+            Self::Save => "Save the crops",
+            Self::Help => "Show the keybindings",
+            Self::ToggleFaces => "Show the faces",
+            Self::EditFaces => "Edit the faces",
+            Self::CompareStrategies => "Compare crop strategies",
+            Self::PrevImage => "Previous wallpaper",
+            Self::NextImage => "Next wallpaper",
+            Self::AlignStart => "Align the crop to the start",
+            Self::AlignCenter => "Align the crop to the center",
+            Self::AlignEnd => "Align the crop to the end",
+            Self::SourceCrop => "Reset to the saved crop",
+            Self::DefaultCrop => "Reset to the default crop",
+            Self::Propagate => "Propagate the crop to all ratios",
+            Self::ZoomIn => "Zoom in",
+            Self::ZoomOut => "Zoom out",
+            Self::Undo => "Undo",
+            Self::Redo => "Redo",
+            Self::NextRatio => "Next ratio",
+        }
+    }
+
+    fn default_combos(self) -> Vec<KeyCombo> {
+        let combos: &[&str] = match self {
+            Self::ToggleFilelist => &["/", "ctrl+f"],
+            Self::Quit => &["ctrl+q"],
+            Self::Save => &["ctrl+s"],
+            Self::Help => &["?"],
+            Self::ToggleFaces => &["f"],
+            Self::EditFaces => &["e"],
+            Self::CompareStrategies => &["c"],
+            Self::PrevImage => &["h"],
+            Self::NextImage => &["l"],
+            Self::AlignStart => &["0"],
+            Self::AlignCenter => &["m"],
+            Self::AlignEnd => &["$"],
+            Self::SourceCrop => &["u"],
+            Self::DefaultCrop => &["d"],
+            Self::Propagate => &["p"],
+            Self::ZoomIn => &["+", "="],
+            Self::ZoomOut => &["-"],
+            Self::Undo => &["ctrl+z"],
+            Self::Redo => &["ctrl+shift+z"],
+            Self::NextRatio => &["t"],
+        };
+
+        combos
+            .iter()
+            .map(|combo| KeyCombo::try_from(*combo).expect("invalid default keybinding"))
+            .collect()
+    }
+}
+
+impl TryFrom<String> for Action {
+    type Error = KeybindingError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or(KeybindingError::UnknownAction(s))
+    }
+}
+
+impl From<Action> for String {
+    fn from(action: Action) -> Self {
+        action.name().to_string()
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+/// key with its modifiers, written as e.g. "ctrl+shift+z"
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyCombo {
+    pub modifiers: Modifiers,
+    /// single character or one of the named keys, letters are stored in lowercase
+    pub key: String,
+}
+
+impl KeyCombo {
+    /// shift is ignored for symbols as they need it on some keyboard layouts but not others
+    fn normalized(&self) -> (String, Modifiers) {
+        let is_symbol =
+            self.key.chars().count() == 1 && !self.key.chars().all(char::is_alphanumeric);
+
+        (
+            self.key.to_lowercase(),
+            Modifiers {
+                shift: self.modifiers.shift && !is_symbol,
+                ..self.modifiers
+            },
+        )
+    }
+
+    /// the key matches along with the modifiers
+    pub fn matches(&self, key: &str, modifiers: Modifiers) -> bool {
+        let pressed = Self {
+            modifiers,
+            key: key.to_string(),
+        };
+
+        self.normalized() == pressed.normalized()
+    }
+}
+
+impl TryFrom<&str> for KeyCombo {
+    type Error = KeybindingError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let (modifiers, key) = match s.rsplit_once('+') {
+            // the key itself is +, e.g. + or ctrl++
+            Some((modifiers, "")) if modifiers.is_empty() || modifiers.ends_with('+') => {
+                (modifiers.strip_suffix('+').unwrap_or_default(), "+")
+            }
+            Some(split) => split,
+            None => ("", s),
+        };
+
+        let mut combo = Self {
+            modifiers: Modifiers::default(),
+            key: String::new(),
+        };
+
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => combo.modifiers.ctrl = true,
+                "shift" => combo.modifiers.shift = true,
+                "alt" => combo.modifiers.alt = true,
+                "meta" | "super" | "cmd" => combo.modifiers.meta = true,
+                _ => return Err(KeybindingError::UnknownModifier(modifier.to_string())),
+            }
+        }
+
+        let mut chars = key.chars();
+        combo.key = match (chars.next(), chars.next()) {
+            // uppercase letters are shorthand for shift
+            (Some(c), None) if c.is_uppercase() => {
+                combo.modifiers.shift = true;
+                c.to_lowercase().to_string()
+            }
+            (Some(c), None) => c.to_string(),
+            _ => NAMED_KEYS
+                .iter()
+                .find(|name| name.eq_ignore_ascii_case(key))
+                .map(|name| (*name).to_string())
+                .ok_or_else(|| KeybindingError::InvalidCombo(s.to_string()))?,
+        };
+
+        Ok(combo)
+    }
+}
+
+impl TryFrom<String> for KeyCombo {
+    type Error = KeybindingError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::try_from(s.as_str())
+    }
+}
+
+impl std::fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let modifiers = [
+            (self.modifiers.ctrl, "ctrl+"),
+            (self.modifiers.shift, "shift+"),
+            (self.modifiers.alt, "alt+"),
+            (self.modifiers.meta, "meta+"),
+        ];
+        for (enabled, name) in modifiers {
+            if enabled {
+                write!(f, "{name}")?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl From<KeyCombo> for String {
+    fn from(combo: KeyCombo) -> Self {
+        combo.to_string()
+    }
+}
+
+/// a single key combo or a list of them
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawBinding {
+    One(KeyCombo),
+    Many(Vec<KeyCombo>),
+}
+
+/// keybindings as they are written in the config file, only the overridden actions are listed
+#[derive(Serialize, Deserialize)]
+struct RawKeybindings(BTreeMap<Action, RawBinding>);
+
+/// key combos for each action, actions that are not configured use the default keys
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawKeybindings", into = "RawKeybindings")]
+pub struct Keybindings {
+    overrides: BTreeMap<Action, Vec<KeyCombo>>,
+}
+
+impl TryFrom<RawKeybindings> for Keybindings {
+    type Error = KeybindingError;
+
+    fn try_from(raw: RawKeybindings) -> Result<Self, Self::Error> {
+        let keybindings = Self {
+            overrides: raw
+                .0
+                .into_iter()
+                .map(|(action, binding)| match binding {
+                    RawBinding::One(combo) => (action, vec![combo]),
+                    RawBinding::Many(combos) => (action, combos),
+                })
+                .collect(),
+        };
+
+        keybindings.validate()?;
+        Ok(keybindings)
+    }
+}
+
+impl From<Keybindings> for RawKeybindings {
+    fn from(keybindings: Keybindings) -> Self {
+        Self(
+            keybindings
+                .overrides
+                .into_iter()
+                .map(|(action, combos)| (action, RawBinding::Many(combos)))
+                .collect(),
+        )
+    }
+}
+
+impl Keybindings {
+    /// the key combos bound to the action
+    pub fn combos(&self, action: Action) -> Vec<KeyCombo> {
+        self.overrides
+            .get(&action)
+            .cloned()
+            .unwrap_or_else(|| action.default_combos())
+    }
+
+    /// each key combo can only be bound to a single action, compared the same way as they are
+    /// matched so combos that only differ by shift on a symbol also conflict
+    fn validate(&self) -> Result<(), KeybindingError> {
+        let mut bound = BTreeMap::new();
+
+        for action in Action::ALL {
+            for combo in self.combos(action) {
+                if RESERVED_KEYS.contains(&combo.key.as_str()) {
+                    return Err(KeybindingError::Reserved(combo));
+                }
+
+                if let Some(other) = bound.insert(combo.normalized(), action) {
+                    return Err(KeybindingError::Conflict(combo, other, action));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the action bound to the key, editor actions are only matched while editing
+    pub fn action(&self, key: &str, modifiers: Modifiers, editing: bool) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .filter(|action| editing || !action.is_editor())
+            .find(|action| {
+                self.combos(*action)
+                    .iter()
+                    .any(|combo| combo.matches(key, modifiers))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONE: Modifiers = Modifiers {
+        ctrl: false,
+        shift: false,
+        alt: false,
+        meta: false,
+    };
+    const CTRL: Modifiers = Modifiers { ctrl: true, ..NONE };
+    const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        ..NONE
+    };
+    const ALT: Modifiers = Modifiers { alt: true, ..NONE };
+    const CTRL_SHIFT: Modifiers = Modifiers {
+        ctrl: true,
+        shift: true,
+        ..NONE
+    };
+
+    fn parse(toml_str: &str) -> Result<Keybindings, toml::de::Error> {
+        toml::from_str(toml_str)
+    }
+
+    #[test]
+    fn test_parse_combo() {
+        let combo = KeyCombo::try_from("ctrl+shift+z").expect("invalid combo");
+        assert_eq!(combo.modifiers, CTRL_SHIFT);
+        assert_eq!(combo.key, "z");
+
+        assert_eq!(
+            KeyCombo::try_from("Z").expect("invalid combo").to_string(),
+            "shift+z"
+        );
+        assert_eq!(
+            KeyCombo::try_from("ctrl++").expect("invalid combo").key,
+            "+"
+        );
+        assert_eq!(KeyCombo::try_from("+").expect("invalid combo").key, "+");
+        assert_eq!(
+            KeyCombo::try_from("escape").expect("invalid combo").key,
+            "Escape"
+        );
+
+        assert!(KeyCombo::try_from("hyper+a").is_err());
+        assert!(KeyCombo::try_from("ctrl+nothing").is_err());
+        assert!(KeyCombo::try_from("ctrl+").is_err());
+    }
+
+    #[test]
+    fn test_default_actions() {
+        let keybindings = Keybindings::default();
+
+        assert_eq!(
+            keybindings.action("z", CTRL_SHIFT, true),
+            Some(Action::Redo)
+        );
+        assert_eq!(
+            keybindings.action("Z", CTRL_SHIFT, true),
+            Some(Action::Redo)
+        );
+        assert_eq!(keybindings.action("z", CTRL, true), Some(Action::Undo));
+        assert_eq!(
+            keybindings.action("f", CTRL, true),
+            Some(Action::ToggleFilelist)
+        );
+        assert_eq!(
+            keybindings.action("f", NONE, true),
+            Some(Action::ToggleFaces)
+        );
+        // symbols are matched regardless of shift
+        assert_eq!(keybindings.action("$", SHIFT, true), Some(Action::AlignEnd));
+        // editor actions are not available outside the editor
+        assert_eq!(keybindings.action("f", NONE, false), None);
+    }
+
+    #[test]
+    fn test_overrides() {
+        let keybindings = parse(
+            r#"
+            prev-image = "left"
+            next-image = ["n", "alt+l"]
+            "#,
+        );
+        // left is not a valid key, arrow keys move the crop
+        assert!(keybindings.is_err());
+
+        let keybindings = parse(
+            r#"
+            prev-image = "j"
+            next-image = ["k", "alt+l"]
+            "#,
+        )
+        .expect("invalid keybindings");
+
+        assert_eq!(keybindings.action("j", NONE, true), Some(Action::PrevImage));
+        assert_eq!(keybindings.action("l", ALT, true), Some(Action::NextImage));
+        assert_eq!(keybindings.action("h", NONE, true), None);
+    }
+
+    #[test]
+    fn test_conflicts() {
+        assert!(parse(r#"next-image = "f""#).is_err());
+        assert!(parse(r#"unknown-action = "x""#).is_err());
+        // shift is ignored when matching symbols, so this is the same key as zoom-out
+        assert!(parse(r#"save = "shift+-""#).is_err());
+        assert!(parse(r#"help = "Escape""#).is_err());
+        assert!(parse(r#"help = "ctrl+F12""#).is_err());
+    }
+}
@@ -0,0 +1,391 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    aspect_ratio::{AspectRatio, AspectRatioError, parse_dimensions},
+    geometry::Geometry,
+};
+
+#[derive(Error, Debug)]
+pub enum LayoutError {
+    #[error("Invalid monitor resolution: {0}")]
+    Resolution(#[from] AspectRatioError),
+    #[error("Invalid layout name {0}, only letters, numbers, - and _ are allowed")]
+    InvalidName(String),
+    #[error("Layout {0} has no monitors")]
+    NoMonitors(String),
+    #[error("Invalid monitor name {0}, it is used as a directory name when exporting")]
+    InvalidMonitorName(String),
+}
+
+/// monitor as it is written in the config file
+#[derive(Serialize, Deserialize)]
+struct RawSpanMonitor {
+    name: String,
+    resolution: String,
+    #[serde(default)]
+    x: u32,
+    #[serde(default)]
+    y: u32,
+}
+
+/// a monitor within a span layout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawSpanMonitor", into = "RawSpanMonitor")]
+pub struct SpanMonitor {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// position of the monitor within the desktop, in pixels
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TryFrom<RawSpanMonitor> for SpanMonitor {
+    type Error = LayoutError;
+
+    fn try_from(raw: RawSpanMonitor) -> Result<Self, Self::Error> {
+        // exported wallpapers are written to a directory named after the monitor
+        if matches!(raw.name.as_str(), "" | "." | "..") || raw.name.contains(['/', '\\']) {
+            return Err(LayoutError::InvalidMonitorName(raw.name));
+        }
+
+        let (width, height) = parse_dimensions(&raw.resolution)?;
+
+        Ok(Self {
+            name: raw.name,
+            width,
+            height,
+            x: raw.x,
+            y: raw.y,
+        })
+    }
+}
+
+impl From<SpanMonitor> for RawSpanMonitor {
+    fn from(monitor: SpanMonitor) -> Self {
+        Self {
+            name: monitor.name,
+            resolution: format!("{}x{}", monitor.width, monitor.height),
+            x: monitor.x,
+            y: monitor.y,
+        }
+    }
+}
+
+/// layout as it is written in the config file
+#[derive(Serialize, Deserialize)]
+struct RawSpanLayout {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    bezel: u32,
+    monitors: Vec<SpanMonitor>,
+}
+
+/// multiple monitors showing a single wallpaper spanning across all of them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawSpanLayout", into = "RawSpanLayout")]
+pub struct SpanLayout {
+    /// used as the key for the span metadata
+    pub name: String,
+    pub description: Option<String>,
+    /// pixels hidden by the bezels between adjacent monitors, so the wallpaper lines up across them
+    pub bezel: u32,
+    pub monitors: Vec<SpanMonitor>,
+}
+
+impl TryFrom<RawSpanLayout> for SpanLayout {
+    type Error = LayoutError;
+
+    fn try_from(raw: RawSpanLayout) -> Result<Self, Self::Error> {
+        // the name is part of the xmp tag
+        if raw.name.is_empty()
+            || !raw
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(LayoutError::InvalidName(raw.name));
+        }
+
+        if raw.monitors.is_empty() {
+            return Err(LayoutError::NoMonitors(raw.name));
+        }
+
+        Ok(Self {
+            name: raw.name,
+            description: raw.description,
+            bezel: raw.bezel,
+            monitors: raw.monitors,
+        })
+    }
+}
+
+impl From<SpanLayout> for RawSpanLayout {
+    fn from(layout: SpanLayout) -> Self {
+        Self {
+            name: layout.name,
+            description: layout.description,
+            bezel: layout.bezel,
+            monitors: layout.monitors,
+        }
+    }
+}
+
+impl SpanLayout {
+    /// area of each monitor within the layout, offset by a bezel for each column / row before it
+    pub fn monitor_rects(&self) -> Vec<Geometry> {
+        // monitors in the same column / row share an edge, so count the edges rather than monitors
+        let gaps = |edges: &[u32], start: u32| {
+            edges.iter().filter(|edge| **edge <= start).unique().count() as u32
+        };
+        let right_edges = self.monitors.iter().map(|m| m.x + m.width).collect_vec();
+        let bottom_edges = self.monitors.iter().map(|m| m.y + m.height).collect_vec();
+
+        let rects = self
+            .monitors
+            .iter()
+            .map(|monitor| {
+                let gaps_x = gaps(&right_edges, monitor.x);
+                let gaps_y = gaps(&bottom_edges, monitor.y);
+
+                Geometry {
+                    x: monitor.x + gaps_x * self.bezel,
+                    y: monitor.y + gaps_y * self.bezel,
+                    w: monitor.width,
+                    h: monitor.height,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // the layout starts at the topmost and leftmost monitor
+        let min_x = rects.iter().map(|r| r.x).min().unwrap_or_default();
+        let min_y = rects.iter().map(|r| r.y).min().unwrap_or_default();
+
+        rects
+            .into_iter()
+            .map(|r| Geometry {
+                x: r.x - min_x,
+                y: r.y - min_y,
+                ..r
+            })
+            .collect()
+    }
+
+    /// dimensions of the entire layout, including the bezels
+    pub fn size(&self) -> (u32, u32) {
+        let rects = self.monitor_rects();
+
+        (
+            rects.iter().map(Geometry::xmax).max().unwrap_or_default(),
+            rects.iter().map(Geometry::ymax).max().unwrap_or_default(),
+        )
+    }
+
+    pub fn aspect_ratio(&self) -> AspectRatio {
+        let (w, h) = self.size();
+        AspectRatio::new(w, h)
+    }
+
+    /// area of the image shown on each monitor for the span crop
+    pub fn monitor_crops(&self, span: &Geometry) -> Vec<(&SpanMonitor, Geometry)> {
+        let (layout_w, layout_h) = self.size();
+        let scale_x = f64::from(span.w) / f64::from(layout_w);
+        let scale_y = f64::from(span.h) / f64::from(layout_h);
+
+        self.monitors
+            .iter()
+            .zip(self.monitor_rects())
+            .map(|(monitor, rect)| {
+                // the edges are rounded rather than the sizes, so adjacent monitors share an edge
+                // and the last monitor can't overrun the span
+                let scale = |edge: u32, scale: f64, max: u32| {
+                    ((f64::from(edge) * scale).round() as u32).min(max)
+                };
+                let (x1, x2) = (
+                    scale(rect.x, scale_x, span.w),
+                    scale(rect.xmax(), scale_x, span.w),
+                );
+                let (y1, y2) = (
+                    scale(rect.y, scale_y, span.h),
+                    scale(rect.ymax(), scale_y, span.h),
+                );
+
+                let geom = Geometry {
+                    x: span.x + x1,
+                    y: span.y + y1,
+                    w: x2 - x1,
+                    h: y2 - y1,
+                };
+
+                (monitor, geom)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, width: u32, height: u32, x: u32) -> SpanMonitor {
+        SpanMonitor {
+            name: name.into(),
+            width,
+            height,
+            x,
+            y: 0,
+        }
+    }
+
+    fn grid(bezel: u32) -> SpanLayout {
+        let monitor_at = |name: &str, x: u32, y: u32| SpanMonitor {
+            y,
+            ..monitor(name, 1920, 1080, x)
+        };
+
+        SpanLayout {
+            name: "grid".into(),
+            description: None,
+            bezel,
+            monitors: vec![
+                monitor_at("top-left", 0, 0),
+                monitor_at("top-right", 1920, 0),
+                monitor_at("bottom-left", 0, 1080),
+                monitor_at("bottom-right", 1920, 1080),
+            ],
+        }
+    }
+
+    fn layout(bezel: u32) -> SpanLayout {
+        SpanLayout {
+            name: "desk".into(),
+            description: None,
+            bezel,
+            monitors: vec![
+                monitor("left", 2560, 1440, 0),
+                monitor("right", 1920, 1080, 2560),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_monitor_rects() {
+        assert_eq!(layout(0).size(), (4480, 1440));
+        assert_eq!(layout(100).size(), (4580, 1440));
+        assert_eq!(
+            layout(100).monitor_rects()[1],
+            Geometry {
+                w: 1920,
+                h: 1080,
+                x: 2660,
+                y: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_grid_rects() {
+        // a single bezel between the columns and between the rows
+        assert_eq!(grid(100).size(), (3940, 2260));
+        assert_eq!(
+            grid(100).monitor_rects()[3],
+            Geometry {
+                w: 1920,
+                h: 1080,
+                x: 2020,
+                y: 1180
+            }
+        );
+    }
+
+    #[test]
+    fn test_monitor_crops() {
+        let span = Geometry {
+            w: 2240,
+            h: 720,
+            x: 100,
+            y: 50,
+        };
+        let layout = layout(0);
+        let crops = layout.monitor_crops(&span);
+
+        assert_eq!(
+            crops[0].1,
+            Geometry {
+                w: 1280,
+                h: 720,
+                x: 100,
+                y: 50
+            }
+        );
+        assert_eq!(
+            crops[1].1,
+            Geometry {
+                w: 960,
+                h: 540,
+                x: 1380,
+                y: 50
+            }
+        );
+    }
+
+    #[test]
+    fn test_odd_monitor_crops() {
+        let layout = SpanLayout {
+            name: "dual".into(),
+            description: None,
+            bezel: 0,
+            monitors: vec![
+                monitor("left", 1920, 1080, 0),
+                monitor("right", 1920, 1080, 1920),
+            ],
+        };
+        // both monitors are 500.5px wide within the span
+        let span = Geometry {
+            w: 1001,
+            h: 281,
+            x: 10,
+            y: 5,
+        };
+        let crops = layout.monitor_crops(&span);
+
+        assert_eq!(crops[0].1.xmax(), crops[1].1.x);
+        assert_eq!(crops[1].1.xmax(), span.xmax());
+        for (_, geom) in &crops {
+            assert!(geom.ymax() <= span.ymax());
+        }
+    }
+
+    #[test]
+    fn test_invalid_layout() {
+        let invalid_name = toml::from_str::<SpanLayout>(
+            r#"
+            name = "my desk"
+            monitors = [{ name = "main", resolution = "1920x1080" }]
+            "#,
+        );
+        let no_monitors = toml::from_str::<SpanLayout>(
+            r#"
+            name = "desk"
+            monitors = []
+            "#,
+        );
+
+        assert!(invalid_name.is_err());
+        assert!(no_monitors.is_err());
+
+        // monitor names are joined to the export directory
+        for name in ["", "..", "../main", "main/left", "main\\\\left"] {
+            let layout = toml::from_str::<SpanLayout>(&format!(
+                r#"
+                name = "desk"
+                monitors = [{{ name = "{name}", resolution = "1920x1080" }}]
+                "#
+            ));
+            assert!(layout.is_err(), "{name} should be invalid");
+        }
+    }
+}
@@ -0,0 +1,498 @@
+use indexmap::IndexMap;
+use itertools::Itertools;
+use rexiv2::Metadata;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+};
+
+use super::{
+    aspect_ratio::AspectRatio, cropper::CROPPER_VERSION, filename, full_path, geometry::Geometry,
+    wallpapers::WallInfoError,
+};
+
+const SIDECAR_EXTENSION: &str = "wallfacer.json";
+
+/// where a crop came from, so crops computed by an older cropper can be refreshed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum CropProvenance {
+    /// default crop, with the version of the cropper that computed it
+    Auto(u32),
+    /// edited by the user
+    User,
+}
+
+impl CropProvenance {
+    /// default crop computed by the current cropper
+    pub const fn auto() -> Self {
+        Self::Auto(CROPPER_VERSION)
+    }
+}
+
+impl std::fmt::Display for CropProvenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Auto(version) => write!(f, "auto-{version}"),
+            Self::User => write!(f, "user"),
+        }
+    }
+}
+
+impl TryFrom<&str> for CropProvenance {
+    type Error = WallInfoError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        if s == "user" {
+            return Ok(Self::User);
+        }
+
+        s.strip_prefix("auto-")
+            .and_then(|version| version.parse().ok())
+            .map(Self::Auto)
+            .ok_or_else(|| WallInfoError::InvalidProvenance(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for CropProvenance {
+    type Error = WallInfoError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::try_from(s.as_str())
+    }
+}
+
+impl From<CropProvenance> for String {
+    fn from(provenance: CropProvenance) -> Self {
+        provenance.to_string()
+    }
+}
+
+/// wallfacer data stored for each wallpaper
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WallMetadata {
+    pub faces: Vec<Geometry>,
+    pub scale: Option<u32>,
+    pub geometries: IndexMap<AspectRatio, Geometry>,
+    /// where each crop came from, missing for crops saved before it was tracked
+    pub provenance: IndexMap<AspectRatio, CropProvenance>,
+    /// crops spanning a multi-monitor layout, keyed by the layout name
+    pub spans: IndexMap<String, Geometry>,
+}
+
+pub trait MetadataStore {
+    /// reads the wallfacer data for the image, empty if there is none
+    fn read(&self, img: &Path) -> Result<WallMetadata, WallInfoError>;
+
+    fn write(&self, img: &Path, data: &WallMetadata) -> Result<(), WallInfoError>;
+
+    /// removes all wallfacer data for the image
+    fn remove(&self, img: &Path) -> Result<(), WallInfoError>;
+
+    fn has_metadata(&self, img: &Path) -> bool;
+
+    /// path of the file holding the data, if it is stored separately from the image
+    fn sidecar_path(&self, _img: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// stores the data as xmp tags within the image itself
+pub struct EmbeddedStore;
+
+impl EmbeddedStore {
+    /// saves the metadata without changing the modified time of the image
+    fn save_preserving_mtime(meta: &Metadata, img: &Path) -> Result<(), WallInfoError> {
+        let prev_modified = std::fs::metadata(img)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        meta.save_to_file(img)?;
+
+        // reset the modified time to maintain sort order
+        if let Some(prev_modified) = prev_modified {
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(img)
+                .and_then(|f| f.set_modified(prev_modified))
+                .ok();
+        }
+
+        Ok(())
+    }
+}
+
+impl MetadataStore for EmbeddedStore {
+    fn read(&self, img: &Path) -> Result<WallMetadata, WallInfoError> {
+        let meta = Metadata::new_from_path(img)?;
+
+        let mut data = WallMetadata::default();
+
+        for tag in meta.get_xmp_tags()? {
+            match tag.as_str() {
+                "Xmp.wallfacer.faces" => {
+                    let face_str = meta.get_tag_string(&tag)?;
+
+                    // empty faces are written as "[]" as rexiv2 seems to return the value of
+                    // the next Xmp field, which is wrong
+                    if face_str != "[]" {
+                        data.faces = face_str
+                            .split(',')
+                            .map(|face| {
+                                Geometry::try_from(face)
+                                    .map_err(|_| WallInfoError::InvalidFace(face.to_string()))
+                            })
+                            .collect::<Result<_, _>>()?;
+                    }
+                }
+                "Xmp.wallfacer.scale" => {
+                    data.scale = meta.get_tag_string(&tag)?.parse::<u32>().ok();
+                }
+                tag if tag.starts_with("Xmp.wallfacer.crop.") => {
+                    let aspect = tag.strip_prefix("Xmp.wallfacer.crop.").unwrap_or_default();
+                    let aspect: AspectRatio = aspect
+                        .try_into()
+                        .map_err(|_| WallInfoError::InvalidAspect(aspect.to_string()))?;
+                    let geom_str = meta.get_tag_string(tag)?;
+                    let geoms: Geometry = geom_str
+                        .as_str()
+                        .try_into()
+                        .map_err(|_| WallInfoError::InvalidCrop(geom_str.clone()))?;
+
+                    data.geometries.insert(aspect, geoms);
+                }
+                tag if tag.starts_with("Xmp.wallfacer.provenance.") => {
+                    let aspect = tag
+                        .strip_prefix("Xmp.wallfacer.provenance.")
+                        .unwrap_or_default();
+                    let aspect: AspectRatio = aspect
+                        .try_into()
+                        .map_err(|_| WallInfoError::InvalidAspect(aspect.to_string()))?;
+                    let provenance = CropProvenance::try_from(meta.get_tag_string(tag)?)?;
+
+                    data.provenance.insert(aspect, provenance);
+                }
+                tag if tag.starts_with("Xmp.wallfacer.span.") => {
+                    let name = tag.strip_prefix("Xmp.wallfacer.span.").unwrap_or_default();
+                    let geom_str = meta.get_tag_string(tag)?;
+                    let geom: Geometry = geom_str
+                        .as_str()
+                        .try_into()
+                        .map_err(|_| WallInfoError::InvalidCrop(geom_str.clone()))?;
+
+                    data.spans.insert(name.to_string(), geom);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(data)
+    }
+
+    fn write(&self, img: &Path, data: &WallMetadata) -> Result<(), WallInfoError> {
+        let meta = Metadata::new_from_path(img)?;
+
+        // clear the previous data so removed crops don't linger
+        for tag in meta.get_xmp_tags()? {
+            if tag.starts_with("Xmp.wallfacer.") {
+                meta.clear_tag(&tag);
+            }
+        }
+
+        // set face metadata
+        let face_strings = if data.faces.is_empty() {
+            "[]".to_string()
+        } else {
+            data.faces
+                .iter()
+                .map(std::string::ToString::to_string)
+                .join(",")
+        };
+
+        meta.set_tag_string("Xmp.wallfacer.faces", &face_strings)?;
+
+        if let Some(scale) = data.scale {
+            meta.set_tag_string("Xmp.wallfacer.scale", &scale.to_string())?;
+        }
+
+        // set crop data
+        for (aspect, geom) in &data.geometries {
+            let crop_key = format!("Xmp.wallfacer.crop.{}", aspect);
+            meta.set_tag_string(&crop_key, &geom.to_string())?;
+        }
+
+        for (aspect, provenance) in &data.provenance {
+            meta.set_tag_string(
+                &format!("Xmp.wallfacer.provenance.{aspect}"),
+                &provenance.to_string(),
+            )?;
+        }
+
+        for (name, geom) in &data.spans {
+            meta.set_tag_string(&format!("Xmp.wallfacer.span.{name}"), &geom.to_string())?;
+        }
+
+        Self::save_preserving_mtime(&meta, img)
+    }
+
+    fn remove(&self, img: &Path) -> Result<(), WallInfoError> {
+        let meta = Metadata::new_from_path(img)?;
+
+        for tag in meta.get_xmp_tags()? {
+            if tag.starts_with("Xmp.wallfacer.") {
+                meta.clear_tag(&tag);
+            }
+        }
+
+        Self::save_preserving_mtime(&meta, img)
+    }
+
+    fn has_metadata(&self, img: &Path) -> bool {
+        Metadata::new_from_path(img)
+            .and_then(|meta| meta.get_tag_string("Xmp.wallfacer.faces"))
+            .is_ok()
+    }
+}
+
+/// on disk format of the sidecar files
+#[derive(Serialize, Deserialize)]
+struct SidecarData {
+    faces: Vec<Geometry>,
+    scale: Option<u32>,
+    crops: BTreeMap<AspectRatio, Geometry>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    provenance: BTreeMap<AspectRatio, CropProvenance>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    spans: BTreeMap<String, Geometry>,
+}
+
+/// stores the data in a json file next to the image, or within a central directory that mirrors
+/// the directories of the images, so images with the same filename don't share a sidecar
+pub struct SidecarStore {
+    pub dir: Option<PathBuf>,
+}
+
+impl SidecarStore {
+    fn path_for(&self, img: &Path) -> PathBuf {
+        let sidecar_name = format!("{}.{SIDECAR_EXTENSION}", filename(img));
+
+        self.dir.as_ref().map_or_else(
+            || img.with_file_name(&sidecar_name),
+            |dir| {
+                let img_path = std::fs::canonicalize(img)
+                    .or_else(|_| std::path::absolute(img))
+                    .unwrap_or_else(|_| img.to_path_buf());
+
+                // strips the root so the absolute path can be joined onto the sidecar directory
+                let mirrored = img_path
+                    .parent()
+                    .into_iter()
+                    .flat_map(Path::components)
+                    .filter(|c| matches!(c, Component::Normal(_)))
+                    .collect::<PathBuf>();
+
+                dir.join(mirrored).join(&sidecar_name)
+            },
+        )
+    }
+}
+
+impl MetadataStore for SidecarStore {
+    fn read(&self, img: &Path) -> Result<WallMetadata, WallInfoError> {
+        let sidecar = self.path_for(img);
+        if !sidecar.exists() {
+            return Ok(WallMetadata::default());
+        }
+
+        let data: SidecarData = serde_json::from_str(&std::fs::read_to_string(sidecar)?)?;
+
+        Ok(WallMetadata {
+            faces: data.faces,
+            scale: data.scale,
+            geometries: data.crops.into_iter().collect(),
+            provenance: data.provenance.into_iter().collect(),
+            spans: data.spans.into_iter().collect(),
+        })
+    }
+
+    fn write(&self, img: &Path, data: &WallMetadata) -> Result<(), WallInfoError> {
+        let sidecar = self.path_for(img);
+        if let Some(parent) = sidecar.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let contents = serde_json::to_string_pretty(&SidecarData {
+            faces: data.faces.clone(),
+            scale: data.scale,
+            crops: data.geometries.clone().into_iter().collect(),
+            provenance: data.provenance.clone().into_iter().collect(),
+            spans: data.spans.clone().into_iter().collect(),
+        })?;
+
+        Ok(std::fs::write(sidecar, contents)?)
+    }
+
+    fn remove(&self, img: &Path) -> Result<(), WallInfoError> {
+        let sidecar = self.path_for(img);
+        if sidecar.exists() {
+            std::fs::remove_file(sidecar)?;
+        }
+
+        Ok(())
+    }
+
+    fn has_metadata(&self, img: &Path) -> bool {
+        self.path_for(img).exists()
+    }
+
+    fn sidecar_path(&self, img: &Path) -> Option<PathBuf> {
+        Some(self.path_for(img))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataBackend {
+    #[default]
+    Embedded,
+    Sidecar,
+}
+
+impl std::fmt::Display for MetadataBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Embedded => write!(f, "embedded"),
+            Self::Sidecar => write!(f, "sidecar"),
+        }
+    }
+}
+
+/// selects where the wallfacer data is stored
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetadataConfig {
+    pub backend: MetadataBackend,
+    /// central directory for the sidecar files, defaults to next to the image
+    pub sidecar_dir: Option<String>,
+}
+
+impl MetadataConfig {
+    /// the same config using a different backend
+    #[must_use]
+    pub fn with_backend(&self, backend: MetadataBackend) -> Self {
+        Self {
+            backend,
+            ..self.clone()
+        }
+    }
+
+    fn sidecar(&self) -> SidecarStore {
+        SidecarStore {
+            dir: self.sidecar_dir.as_deref().map(full_path),
+        }
+    }
+}
+
+impl MetadataStore for MetadataConfig {
+    fn read(&self, img: &Path) -> Result<WallMetadata, WallInfoError> {
+        match self.backend {
+            MetadataBackend::Embedded => EmbeddedStore.read(img),
+            MetadataBackend::Sidecar => self.sidecar().read(img),
+        }
+    }
+
+    fn write(&self, img: &Path, data: &WallMetadata) -> Result<(), WallInfoError> {
+        match self.backend {
+            MetadataBackend::Embedded => EmbeddedStore.write(img, data),
+            MetadataBackend::Sidecar => self.sidecar().write(img, data),
+        }
+    }
+
+    fn remove(&self, img: &Path) -> Result<(), WallInfoError> {
+        match self.backend {
+            MetadataBackend::Embedded => EmbeddedStore.remove(img),
+            MetadataBackend::Sidecar => self.sidecar().remove(img),
+        }
+    }
+
+    fn has_metadata(&self, img: &Path) -> bool {
+        match self.backend {
+            MetadataBackend::Embedded => EmbeddedStore.has_metadata(img),
+            MetadataBackend::Sidecar => self.sidecar().has_metadata(img),
+        }
+    }
+
+    fn sidecar_path(&self, img: &Path) -> Option<PathBuf> {
+        match self.backend {
+            MetadataBackend::Embedded => EmbeddedStore.sidecar_path(img),
+            MetadataBackend::Sidecar => self.sidecar().sidecar_path(img),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_path() {
+        let store = SidecarStore { dir: None };
+        assert_eq!(
+            store.path_for(Path::new("/walls/a/img.jpg")),
+            PathBuf::from("/walls/a/img.jpg.wallfacer.json")
+        );
+
+        // images with the same filename in different directories
+        let store = SidecarStore {
+            dir: Some(PathBuf::from("/sidecars")),
+        };
+        assert_eq!(
+            store.path_for(Path::new("/walls/a/img.jpg")),
+            PathBuf::from("/sidecars/walls/a/img.jpg.wallfacer.json")
+        );
+        assert_eq!(
+            store.path_for(Path::new("/walls/b/img.jpg")),
+            PathBuf::from("/sidecars/walls/b/img.jpg.wallfacer.json")
+        );
+    }
+
+    #[test]
+    fn test_provenance_parse() {
+        assert_eq!(
+            CropProvenance::try_from("user").ok(),
+            Some(CropProvenance::User)
+        );
+        assert_eq!(
+            CropProvenance::try_from("auto-3").ok(),
+            Some(CropProvenance::Auto(3))
+        );
+
+        for invalid in ["", "auto", "auto-", "auto-x", "User", "manual"] {
+            assert!(
+                CropProvenance::try_from(invalid).is_err(),
+                "{invalid} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn test_provenance_roundtrip() {
+        for provenance in [
+            CropProvenance::User,
+            CropProvenance::auto(),
+            CropProvenance::Auto(0),
+        ] {
+            assert_eq!(
+                CropProvenance::try_from(provenance.to_string()).ok(),
+                Some(provenance)
+            );
+
+            let json = serde_json::to_string(&provenance).expect("could not serialize");
+            assert_eq!(
+                serde_json::from_str::<CropProvenance>(&json).ok(),
+                Some(provenance)
+            );
+        }
+    }
+}
@@ -7,11 +7,23 @@ use std::{
 
 pub mod aspect_ratio;
 pub mod cli;
+pub mod command;
 pub mod config;
+pub mod crop_strategy;
+pub mod crop_transfer;
 pub mod cropper;
+pub mod detector;
+pub mod energy;
 pub mod geometry;
+pub mod index;
+pub mod keybindings;
+pub mod layout;
+pub mod metadata;
+pub mod optimizer;
 pub mod pipeline;
+pub mod upscaler;
 pub mod wallpapers;
+pub mod workspace;
 
 pub fn full_path(p: &str) -> PathBuf {
     p.strip_prefix("~/").map_or_else(
@@ -99,6 +111,25 @@ where
     false
 }
 
+/// prints the wallpapers a command failed on along with their errors
+pub fn report_failures<P, E>(action: &str, failed: &[(P, E)], total: usize)
+where
+    P: AsRef<Path>,
+    E: std::fmt::Display,
+{
+    if failed.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "\n{} / {total} wallpapers could not be {action}:",
+        failed.len()
+    );
+    for (path, e) in failed {
+        eprintln!("{}: {e}", path.as_ref().display());
+    }
+}
+
 pub fn filter_images<P>(dir: P) -> impl Iterator<Item = PathBuf>
 where
     P: AsRef<Path> + std::fmt::Debug,
@@ -0,0 +1,356 @@
+use image::{
+    ImageReader,
+    codecs::{
+        jpeg::JpegEncoder,
+        png::{self, PngEncoder},
+        webp::WebPEncoder,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use thiserror::Error;
+
+use super::command::{CommandError, run_command};
+
+#[derive(Error, Debug)]
+pub enum OptimizerError {
+    #[error(transparent)]
+    Command(#[from] CommandError),
+    #[error("{0} did not produce {1}")]
+    MissingOutput(String, PathBuf),
+    #[error("Unable to encode image: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Unable to write image: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Unsupported image format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Lossy webp encoding requires the external optimizer backend")]
+    LossyWebp,
+}
+
+pub trait Optimizer {
+    /// optimizes the input image, the format is determined by the extension of the output
+    fn optimize(&self, input: &Path, output: &Path) -> Result<(), OptimizerError>;
+}
+
+fn extension(path: &Path) -> Result<String, OptimizerError> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .ok_or_else(|| OptimizerError::UnsupportedFormat(path.display().to_string()))
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PngCompression {
+    Fast,
+    Default,
+    #[default]
+    Best,
+}
+
+impl From<PngCompression> for png::CompressionType {
+    fn from(compression: PngCompression) -> Self {
+        match compression {
+            PngCompression::Fast => Self::Fast,
+            PngCompression::Default => Self::Default,
+            PngCompression::Best => Self::Best,
+        }
+    }
+}
+
+/// jpeg quality used by the builtin backend to convert other formats to jpeg when none is
+/// configured
+const DEFAULT_JPEG_QUALITY: u8 = 95;
+
+/// removes the exif, xmp, iptc and comment segments from a jpeg without re-encoding it, segments
+/// describing the image data such as the color profile are kept
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    let mut rest = data.strip_prefix(&[0xFF, 0xD8])?;
+    let mut stripped = Vec::with_capacity(data.len());
+    stripped.extend_from_slice(&[0xFF, 0xD8]);
+
+    loop {
+        match *rest {
+            // the compressed image data follows the start of scan until the end of the file
+            [0xFF, 0xDA, ..] => {
+                stripped.extend_from_slice(rest);
+                return Some(stripped);
+            }
+            [0xFF, marker, hi, lo, ..] => {
+                // the length includes itself but not the marker
+                let end = usize::from(u16::from_be_bytes([hi, lo])) + 2;
+                let segment = rest.get(..end)?;
+                if !matches!(marker, 0xE1 | 0xED | 0xFE) {
+                    stripped.extend_from_slice(segment);
+                }
+                rest = rest.get(end..)?;
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// encodes the images using the image crate, which can only encode webp losslessly
+pub struct BuiltinOptimizer {
+    /// jpegs only have their metadata stripped without a quality, other formats are converted
+    /// with the default quality
+    pub jpeg_quality: Option<u8>,
+    pub png_compression: PngCompression,
+    /// lossy webp isn't supported, so a quality is rejected instead of silently ignored
+    pub webp_quality: Option<u8>,
+}
+
+impl Optimizer for BuiltinOptimizer {
+    fn optimize(&self, input: &Path, output: &Path) -> Result<(), OptimizerError> {
+        let ext = extension(output)?;
+        if ext == "webp" && self.webp_quality.is_some() {
+            return Err(OptimizerError::LossyWebp);
+        }
+
+        // jpegs are only re-encoded when a quality is set, so they can't get worse or larger
+        if matches!(ext.as_str(), "jpg" | "jpeg")
+            && self.jpeg_quality.is_none()
+            && let Some(stripped) = strip_jpeg(&std::fs::read(input)?)
+        {
+            std::fs::write(output, stripped)?;
+            return Ok(());
+        }
+
+        // decoded before creating the output, as the input and output can be the same file
+        let img = ImageReader::open(input)?.with_guessed_format()?.decode()?;
+        let writer = BufWriter::new(File::create(output)?);
+
+        match ext.as_str() {
+            // jpeg doesn't support transparency
+            "jpg" | "jpeg" => img
+                .to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(
+                    writer,
+                    self.jpeg_quality.unwrap_or(DEFAULT_JPEG_QUALITY),
+                ))?,
+            "png" => img.write_with_encoder(PngEncoder::new_with_quality(
+                writer,
+                self.png_compression.into(),
+                png::FilterType::Adaptive,
+            ))?,
+            "webp" => img.write_with_encoder(WebPEncoder::new_lossless(writer))?,
+            _ => return Err(OptimizerError::UnsupportedFormat(ext)),
+        }
+
+        Ok(())
+    }
+}
+
+/// optimizes using cwebp, jpegoptim and oxipng, which need to be installed
+pub struct ExternalOptimizer {
+    /// jpegoptim is lossless without a quality
+    pub jpeg_quality: Option<u8>,
+    /// cwebp quality, defaults to 100
+    pub webp_quality: Option<u8>,
+    /// cwebp compression method, 0 - 6
+    pub webp_effort: u8,
+}
+
+impl ExternalOptimizer {
+    fn run(cmd: &mut Command, name: &str, output: &Path) -> Result<(), OptimizerError> {
+        run_command(cmd, name)?;
+
+        if !output.exists() {
+            return Err(OptimizerError::MissingOutput(
+                name.to_string(),
+                output.to_path_buf(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Optimizer for ExternalOptimizer {
+    fn optimize(&self, input: &Path, output: &Path) -> Result<(), OptimizerError> {
+        match extension(output)?.as_str() {
+            "jpg" | "jpeg" => {
+                let dest = output.parent().ok_or_else(|| {
+                    OptimizerError::UnsupportedFormat(output.display().to_string())
+                })?;
+
+                let mut cmd = Command::new("jpegoptim");
+                cmd.arg("--strip-all");
+                if let Some(quality) = self.jpeg_quality {
+                    cmd.arg(format!("--max={quality}"));
+                }
+
+                Self::run(cmd.arg(input).arg("--dest").arg(dest), "jpegoptim", output)
+            }
+            "png" => Self::run(
+                Command::new("oxipng")
+                    .args(["--opt", "max"])
+                    .arg(input)
+                    .arg("--out")
+                    .arg(output),
+                "oxipng",
+                output,
+            ),
+            "webp" => Self::run(
+                Command::new("cwebp")
+                    .arg("-q")
+                    .arg(self.webp_quality.unwrap_or(100).to_string())
+                    .arg("-m")
+                    .arg(self.webp_effort.to_string())
+                    .args(["-mt", "-af"])
+                    .arg(input)
+                    .arg("-o")
+                    .arg(output),
+                "cwebp",
+                output,
+            ),
+            ext => Err(OptimizerError::UnsupportedFormat(ext.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptimizerBackend {
+    #[default]
+    Builtin,
+    External,
+}
+
+impl std::fmt::Display for OptimizerBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Builtin => write!(f, "builtin"),
+            Self::External => write!(f, "external"),
+        }
+    }
+}
+
+/// selects how wallpapers are encoded when adding them
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptimizerConfig {
+    pub backend: OptimizerBackend,
+    /// 1 - 100, jpegs are only stripped of their metadata losslessly when unset
+    pub jpeg_quality: Option<u8>,
+    /// defaults to best, only used by the builtin backend
+    pub png_compression: Option<PngCompression>,
+    /// 0 - 100, the external backend defaults to cwebp -q 100, which is still lossy, while the
+    /// builtin backend always encodes webp losslessly and rejects a quality
+    pub webp_quality: Option<u8>,
+    /// 0 - 6, higher is slower but smaller, defaults to 6, only used by the external backend
+    pub webp_effort: Option<u8>,
+}
+
+impl OptimizerConfig {
+    /// options that are set but ignored by the configured backend
+    pub fn unsupported_options(&self) -> Vec<&'static str> {
+        match self.backend {
+            OptimizerBackend::Builtin => self.webp_effort.map(|_| "webp_effort"),
+            OptimizerBackend::External => self.png_compression.map(|_| "png_compression"),
+        }
+        .into_iter()
+        .collect()
+    }
+}
+
+impl Optimizer for OptimizerConfig {
+    fn optimize(&self, input: &Path, output: &Path) -> Result<(), OptimizerError> {
+        match self.backend {
+            OptimizerBackend::Builtin => BuiltinOptimizer {
+                jpeg_quality: self.jpeg_quality.map(|q| q.clamp(1, 100)),
+                png_compression: self.png_compression.unwrap_or_default(),
+                webp_quality: self.webp_quality,
+            }
+            .optimize(input, output),
+            OptimizerBackend::External => ExternalOptimizer {
+                jpeg_quality: self.jpeg_quality.map(|q| q.clamp(1, 100)),
+                webp_quality: self.webp_quality.map(|q| q.min(100)),
+                webp_effort: self.webp_effort.unwrap_or(6).min(6),
+            }
+            .optimize(input, output),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+    use image::RgbImage;
+
+    #[test]
+    fn test_builtin_webp() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let input = workspace.dir().join("input.png");
+        let output = workspace.dir().join("output.webp");
+        RgbImage::new(8, 8)
+            .save(&input)
+            .expect("could not write image");
+
+        let mut cfg = OptimizerConfig::default();
+        cfg.optimize(&input, &output)
+            .expect("lossless webp should be supported");
+        assert!(output.exists());
+
+        cfg.webp_quality = Some(80);
+        assert!(matches!(
+            cfg.optimize(&input, &output),
+            Err(OptimizerError::LossyWebp)
+        ));
+    }
+
+    #[test]
+    fn test_builtin_jpeg() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let input = workspace.dir().join("input.jpg");
+        let output = workspace.dir().join("output.jpg");
+        RgbImage::new(8, 8)
+            .save(&input)
+            .expect("could not write image");
+
+        // insert an exif segment after the start of image marker
+        let encoded = std::fs::read(&input).expect("could not read image");
+        let mut with_exif = encoded[..2].to_vec();
+        with_exif.extend_from_slice(&[0xFF, 0xE1, 0x00, 0x08]);
+        with_exif.extend_from_slice(b"Exif\0\0");
+        with_exif.extend_from_slice(&encoded[2..]);
+        std::fs::write(&input, &with_exif).expect("could not write image");
+
+        // the metadata is stripped without re-encoding the image
+        let cfg = OptimizerConfig::default();
+        cfg.optimize(&input, &output)
+            .expect("could not optimize jpeg");
+        assert_eq!(
+            std::fs::read(&output).expect("could not read output"),
+            encoded
+        );
+
+        // other formats are still converted
+        let png = workspace.dir().join("input.png");
+        RgbImage::new(8, 8)
+            .save(&png)
+            .expect("could not write image");
+        cfg.optimize(&png, &output)
+            .expect("could not convert png to jpeg");
+        assert!(image::open(&output).is_ok());
+    }
+
+    #[test]
+    fn test_unsupported_options() {
+        let mut cfg = OptimizerConfig {
+            webp_effort: Some(4),
+            ..OptimizerConfig::default()
+        };
+        assert_eq!(cfg.unsupported_options(), vec!["webp_effort"]);
+
+        cfg.backend = OptimizerBackend::External;
+        assert!(cfg.unsupported_options().is_empty());
+    }
+}
@@ -1,183 +1,392 @@
 use std::{
-    collections::HashMap,
-    io::Write,
+    collections::HashSet,
+    fs::File,
+    io::{BufRead, BufReader, Write},
     path::{Path, PathBuf},
-    process::{Command, Stdio},
-    time::{Duration, Instant},
+    sync::{Mutex, MutexGuard},
 };
 
 use itertools::Itertools;
+use serde::Serialize;
+use thiserror::Error;
 
-use crate::filter_images;
+use crate::{PathBufVecExt, filter_images};
 
 use super::{
-    Bbox, PathBufExt, config::Config, cropper::Cropper, run_wallfacer, wallpapers::WallInfo,
+    PathBufExt,
+    config::Config,
+    cropper::Cropper,
+    detector::{DetectorError, FaceDetector},
+    index::WallIndex,
+    metadata::CropProvenance,
+    optimizer::{Optimizer, OptimizerError},
+    run_wallfacer,
+    upscaler::{Upscaler, UpscalerBackend, UpscalerError},
+    wallpapers::{WallInfo, WallInfoError},
+    workspace::TempWorkspace,
 };
 
 const WEBP_MAX_DIMENSION: u32 = 16383;
+const JOURNAL_FILE: &str = ".wallfacer-add.journal";
+
+#[derive(Error, Debug)]
+pub enum PipelineError {
+    #[error(transparent)]
+    WallInfo(#[from] WallInfoError),
+    #[error("Unable to detect faces: {0}")]
+    Detector(#[from] DetectorError),
+    #[error("Unable to upscale: {0}")]
+    Upscaler(#[from] UpscalerError),
+    #[error("Unable to optimize: {0}")]
+    Optimizer(#[from] OptimizerError),
+    #[error("Unable to read image: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Unable to write image: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Image is too small to be upscaled")]
+    TooSmall,
+    #[error("Upscaled image would be too large")]
+    TooLarge,
+    #[error("Orphan wallpapers without metadata: {0}")]
+    Orphans(String),
+}
 
-/// waits for the images to be written to disk
-fn wait_for_image(path: &Path) {
-    // wait for at most 5 minutes
-    const TIMEOUT: Duration = Duration::from_secs(5 * 60);
-
-    let start_time = Instant::now();
-    while !path.exists() {
-        if start_time.elapsed() >= TIMEOUT {
-            eprintln!(
-                "Timed out waiting for {} after {}",
-                path.display(),
-                TIMEOUT.as_secs()
-            );
-            std::process::exit(1);
+/// what happened to an image passed to the pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddOutcome {
+    Processed,
+    /// already exists in the output directory, or was completed by an interrupted run
+    Skipped,
+}
+
+/// what adding an image would do, used for dry runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum AddPlan {
+    /// faces are detected, then the image is upscaled by the scale and optimized
+    Process {
+        scale: u32,
+        /// an existing wallpaper is overwritten, as it was forced or edited
+        replace: bool,
+    },
+    /// already exists but still needs its crops checked, so it is previewed again
+    Preview,
+    Skip,
+}
+
+impl std::fmt::Display for AddPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Process { scale, replace } => {
+                write!(
+                    f,
+                    "{}: detect",
+                    if *replace { "Replace" } else { "Process" }
+                )?;
+                if *scale > 1 {
+                    write!(f, " -> upscale x{scale}")?;
+                }
+                write!(f, " -> optimize")
+            }
+            Self::Preview => write!(f, "Preview"),
+            Self::Skip => write!(f, "Skip"),
         }
-        std::thread::sleep(Duration::from_millis(100));
     }
 }
 
-pub fn optimize_webp(
-    infile: &PathBuf,
-    outfile: &PathBuf,
-) -> Result<std::process::ExitStatus, std::io::Error> {
-    Command::new("cwebp")
-        .args(["-q", "100", "-m", "6", "-mt", "-af"])
-        .arg(infile)
-        .arg("-o")
-        .arg(outfile)
-        // silence output
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .and_then(|mut c| c.wait())
+/// records the progress of an add within the output directory, so an interrupted run can be
+/// resumed without reprocessing the images that were completed
+struct Journal {
+    path: PathBuf,
+    file: Option<Mutex<File>>,
+    done: HashSet<PathBuf>,
 }
 
-pub fn optimize_jpg(
-    infile: &PathBuf,
-    outfile: &Path,
-) -> Result<std::process::ExitStatus, std::io::Error> {
-    Command::new("jpegoptim")
-        .arg("--strip-all")
-        .arg(infile)
-        .arg("--dest")
-        .arg(
-            outfile.parent().unwrap_or_else(|| {
-                panic!("could not get parent directory for {}", infile.display())
-            }),
-        )
-        // silence output
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .and_then(|mut c| c.wait())
-}
+impl Journal {
+    /// reads the outputs that were only partially written and the inputs that were completed
+    fn read(path: &Path) -> std::io::Result<(HashSet<PathBuf>, HashSet<PathBuf>)> {
+        let mut partial = HashSet::new();
+        let mut done = HashSet::new();
+        if let Ok(prev) = File::open(path) {
+            for line in BufReader::new(prev).lines() {
+                match line?.split_once('\t') {
+                    Some(("start", out_path)) => {
+                        partial.insert(PathBuf::from(out_path));
+                    }
+                    Some(("end", out_path)) => {
+                        partial.remove(Path::new(out_path));
+                    }
+                    Some(("done", img)) => {
+                        done.insert(PathBuf::from(img));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok((partial, done))
+    }
+
+    /// loads the journal of a previous run, removing any outputs that were only partially written
+    fn open(output: &Path) -> std::io::Result<Self> {
+        let path = output.join(JOURNAL_FILE);
+        let (partial, done) = Self::read(&path)?;
+
+        for out_path in partial {
+            if out_path.exists() {
+                std::fs::remove_file(out_path)?;
+            }
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+
+        Ok(Self {
+            path,
+            file: Some(Mutex::new(file)),
+            done,
+        })
+    }
+
+    /// loads the journal of a previous run without modifying anything, nothing is recorded
+    fn read_only(output: &Path) -> std::io::Result<Self> {
+        let path = output.join(JOURNAL_FILE);
+        let (_, done) = Self::read(&path)?;
+
+        Ok(Self {
+            path,
+            file: None,
+            done,
+        })
+    }
+
+    /// inputs are recorded by their full path, as they can be passed relative to any directory
+    fn input_key(img: &Path) -> PathBuf {
+        img.canonicalize().unwrap_or_else(|_| img.to_path_buf())
+    }
+
+    fn is_done(&self, img: &Path) -> bool {
+        self.done.contains(&Self::input_key(img))
+    }
+
+    fn mark_done(&self, img: &Path) -> std::io::Result<()> {
+        self.record("done", &Self::input_key(img))
+    }
+
+    /// "start" and "end" surround writing an output, "done" marks an input as completed
+    fn record(&self, kind: &str, path: &Path) -> std::io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
 
-pub fn optimize_png(
-    infile: &PathBuf,
-    outfile: &PathBuf,
-) -> Result<std::process::ExitStatus, std::io::Error> {
-    Command::new("oxipng")
-        .args(["--opt", "max"])
-        .arg(infile)
-        .arg("--out")
-        .arg(outfile)
-        // silence output
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .and_then(|mut c| c.wait())
+        let mut file = file.lock().expect("journal lock poisoned");
+        writeln!(file, "{kind}\t{}", path.display())?;
+        file.flush()
+    }
+
+    /// the run completed without failures, so there is nothing to resume
+    fn remove(self) -> std::io::Result<()> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+
+        drop(self.file);
+        std::fs::remove_file(self.path)
+    }
 }
 
-#[derive(Default)]
 pub struct WallpaperPipeline {
     config: Config,
     format: Option<String>,
-    to_preview: Vec<PathBuf>,
+    to_preview: Mutex<Vec<PathBuf>>,
     output: PathBuf,
+    index: Mutex<WallIndex>,
+    journal: Journal,
+    /// intermediate files from upscaling and optimizing, not created for dry runs
+    workspace: Option<TempWorkspace>,
+    /// gpu upscalers are run one at a time, as multiple instances would compete for vram
+    upscale_lock: Mutex<()>,
+    /// face detection models on the gpu are also run one at a time, for the same reason
+    detect_lock: Mutex<()>,
 }
 
 impl WallpaperPipeline {
-    pub fn new(cfg: &Config, format: Option<String>, output: PathBuf) -> Self {
+    pub fn new(
+        cfg: &Config,
+        format: Option<String>,
+        output: PathBuf,
+    ) -> Result<Self, PipelineError> {
+        let journal = Journal::open(&output)?;
+
+        if !journal.done.is_empty() {
+            println!(
+                "Resuming previous run, {} images were already processed",
+                journal.done.len()
+            );
+        }
+
+        Self::with_journal(
+            cfg,
+            format,
+            output,
+            journal,
+            Some(TempWorkspace::new("add")?),
+        )
+    }
+
+    /// a pipeline that only plans the images, without modifying the output directory
+    pub fn dry_run(
+        cfg: &Config,
+        format: Option<String>,
+        output: PathBuf,
+    ) -> Result<Self, PipelineError> {
+        let journal = Journal::read_only(&output)?;
+        Self::with_journal(cfg, format, output, journal, None)
+    }
+
+    fn with_journal(
+        cfg: &Config,
+        format: Option<String>,
+        output: PathBuf,
+        journal: Journal,
+        workspace: Option<TempWorkspace>,
+    ) -> Result<Self, PipelineError> {
+        let mut index = WallIndex::load(&cfg.metadata);
+
         // check that images from wallpapers dir all have metadata
         let orphan_wallpapers = filter_images(&output)
-            .filter(|img| !WallInfo::has_metadata(img))
+            .filter(|img| !index.has_metadata(img))
+            .map(|img| img.display().to_string())
             .collect_vec();
 
         if !orphan_wallpapers.is_empty() {
-            for img in orphan_wallpapers {
-                eprintln!("orphan wallpaper: {}", img.display());
-            }
-            std::process::exit(1);
+            return Err(PipelineError::Orphans(orphan_wallpapers.join(", ")));
         }
 
-        Self {
+        Ok(Self {
             format,
             config: cfg.clone(),
-            to_preview: Vec::new(),
+            to_preview: Mutex::new(Vec::new()),
             output,
-        }
+            index: Mutex::new(index),
+            journal,
+            workspace,
+            upscale_lock: Mutex::new(()),
+            detect_lock: Mutex::new(()),
+        })
     }
 
-    pub fn add_image(&mut self, img: &PathBuf, force: bool, status_line: &str) {
-        let (width, height) = image::image_dimensions(img)
-            .unwrap_or_else(|_| panic!("could not get image dimensions for {}", img.display()));
+    fn index(&self) -> MutexGuard<'_, WallIndex> {
+        self.index.lock().expect("index lock poisoned")
+    }
 
-        let out_path = self
-            .format
+    const fn workspace(&self) -> &TempWorkspace {
+        self.workspace
             .as_ref()
-            .map_or_else(|| img.clone(), |ext| img.with_extension(ext.as_str()))
-            .with_directory(&self.output);
-
-        // detect -> upscale -> optimize -> save
-        if out_path.exists() && !force {
-            // check if corresponding WallInfo exists
-            let info = WallInfo::new_from_file(&out_path);
-
-            // image has been edited (different aspect ratio), re-process the image
-            if info.width / width != info.height / height {
-                self.detect(img, status_line);
-                return;
-            }
+            .expect("images are not processed in a dry run")
+    }
+
+    fn queue_preview(&self, img: PathBuf) {
+        self.to_preview
+            .lock()
+            .expect("preview lock poisoned")
+            .push(img);
+    }
+
+    fn out_path(&self, img: &Path) -> PathBuf {
+        self.format
+            .as_ref()
+            .map_or_else(|| img.to_path_buf(), |ext| img.with_extension(ext.as_str()))
+            .with_directory(&self.output)
+    }
+
+    /// scale the image will be upscaled by, if it can be
+    fn target_scale(&self, info: &WallInfo) -> Result<u32, PipelineError> {
+        let scale = info
+            .get_target_scale(&self.config)
+            .ok_or(PipelineError::TooSmall)?;
+
+        // edge case, webp too large
+        if info.width * scale > WEBP_MAX_DIMENSION || info.height * scale > WEBP_MAX_DIMENSION {
+            return Err(PipelineError::TooLarge);
+        }
 
-            // re-preview if no / multiple faces detected and still using default crop
-            if info.faces.len() != 1 && info.is_default_crops(&self.config.sorted_resolutions()) {
-                self.to_preview.push(out_path);
+        Ok(scale)
+    }
+
+    /// decides what adding the image would do, without modifying anything
+    pub fn plan(&self, img: &PathBuf, force: bool) -> Result<AddPlan, PipelineError> {
+        // forced images are processed again even if an earlier run completed them
+        let done = !force && self.journal.is_done(img);
+
+        let (width, height) = image::image_dimensions(img)?;
+        let out_path = self.out_path(img);
+        let exists = out_path.exists();
+
+        let process = || -> Result<AddPlan, PipelineError> {
+            let info = WallInfo {
+                width,
+                height,
+                ..Default::default()
+            };
+
+            Ok(AddPlan::Process {
+                scale: self.target_scale(&info)?,
+                replace: exists,
+            })
+        };
+
+        if !exists || force {
+            return process();
+        }
+
+        // check if corresponding WallInfo exists
+        let info = self.index().get(&out_path)?;
+
+        // image has been edited (different aspect ratio), re-process the image, unless it was
+        // completed by an interrupted run and only needs to be checked for a preview
+        if !done && info.width / width != info.height / height {
+            return process();
+        }
+
+        // re-preview if no / multiple faces detected and still using default crop
+        if info.faces.len() != 1 && info.is_default_crops(&self.config.sorted_resolutions()) {
+            return Ok(AddPlan::Preview);
+        }
+
+        Ok(AddPlan::Skip)
+    }
+
+    /// safe to call from multiple threads, each image goes through detect -> upscale -> optimize
+    pub fn add_image(&self, img: &PathBuf, force: bool) -> Result<AddOutcome, PipelineError> {
+        match self.plan(img, force)? {
+            AddPlan::Process { .. } => {
+                self.detect(img)?;
+                self.journal.mark_done(img)?;
+                Ok(AddOutcome::Processed)
             }
-        } else {
-            self.detect(img, status_line);
+            // not marked as done, so a resumed run previews it again
+            AddPlan::Preview => {
+                self.queue_preview(self.out_path(img));
+                Ok(AddOutcome::Skipped)
+            }
+            AddPlan::Skip => Ok(AddOutcome::Skipped),
         }
     }
 
     // detect is first step as detection in a smaller image is faster
-    pub fn detect(&mut self, img: &PathBuf, status_line: &str) {
-        print!("{status_line} Detecting faces...{}", " ".repeat(10));
-        std::io::stdout().flush().expect("could not flush stdout");
-
-        // get output of anime face detector
-        let child = Command::new("anime-face-detector")
-            .arg(img)
-            .stdout(Stdio::piped())
-            .output()
-            .expect("failed to spawn anime-face-detector");
-
-        let line = std::str::from_utf8(&child.stdout)
-            .expect("could not convert output to str")
-            .strip_suffix("\n")
-            .unwrap_or_default()
-            .to_string();
-
-        let detections: HashMap<PathBuf, Vec<Bbox>> =
-            serde_json::from_str(&line).expect("could not deserialize faces");
-        // discard the path and use the first value since only a single file is passed at a time
-        let faces = detections
-            .values()
-            .next()
-            .expect("error parsing detected faces")
-            .iter()
-            .map(|f: &Bbox| Bbox::to_face(f))
-            .collect_vec();
+    pub fn detect(&self, img: &PathBuf) -> Result<(), PipelineError> {
+        let model = self
+            .config
+            .detector
+            .is_exclusive()
+            .then(|| self.detect_lock.lock().expect("detect lock poisoned"));
+        let faces = self.config.detector.detect(img)?;
+        drop(model);
 
-        let (width, height) = image::image_dimensions(img)
-            .unwrap_or_else(|_| panic!("could not get image dimensions: {}", img.display()));
+        let (width, height) = image::image_dimensions(img)?;
 
         // create WallInfo and save it
         self.upscale(
@@ -190,56 +399,19 @@ impl WallpaperPipeline {
                 scale: Some(1),
                 ..Default::default()
             },
-            status_line,
-        );
+        )
     }
 
-    pub fn upscale(&mut self, img: &PathBuf, info: WallInfo, status_line: &str) {
-        let scale = info
-            .get_target_scale(self.config.min_width, self.config.min_height)
-            .unwrap_or_else(|| {
-                eprintln!("{} is too small to be upscaled!", img.display());
-                std::process::exit(1);
-            });
-
-        // edge case, webp too large
-        assert!(
-            !(info.width * scale > WEBP_MAX_DIMENSION || info.height * scale > WEBP_MAX_DIMENSION),
-            "could not upscale {}, too large",
-            img.display()
-        );
-
-        if scale == 1 {
-            return self.optimize(img, &info, status_line);
-        }
-
-        print!("{status_line} Upscaling image...{}", " ".repeat(10));
-        std::io::stdout().flush().expect("could not flush stdout");
-
-        let mut dest = img.with_directory("/tmp");
-
-        if let Some(ext) = &self.format {
-            dest = dest.with_extension(ext);
-        }
-
-        Command::new("realcugan-ncnn-vulkan")
-            .arg("-i")
-            .arg(img)
-            .arg("-s")
-            .arg(scale.to_string())
-            .arg("-o")
-            .arg(&dest)
-            // silence output
-            .stderr(Stdio::null())
-            .spawn()
-            .and_then(|mut c| c.wait())
-            .expect("could not run realcugan-ncnn-vulkan");
+    pub fn upscale(&self, img: &PathBuf, info: WallInfo) -> Result<(), PipelineError> {
+        let scale = self.target_scale(&info)?;
 
         // update wallinfo with scaled properties
         let scaled_width = info.width * scale;
         let scaled_height = info.height * scale;
         let scaled_faces: Vec<_> = info.faces.into_iter().map(|face| face * scale).collect();
-        let cropper = Cropper::new(&scaled_faces, scaled_width, scaled_height);
+
+        let cropper = Cropper::new(&scaled_faces, scaled_width, scaled_height)
+            .with_strategy(self.config.crop.strategy);
 
         let final_info = WallInfo {
             width: scaled_width,
@@ -253,45 +425,67 @@ impl WallpaperPipeline {
                 .iter()
                 .map(|ratio| (ratio.clone(), cropper.crop(ratio)))
                 .collect(),
+            provenance: self
+                .config
+                .sorted_resolutions()
+                .into_iter()
+                .map(|ratio| (ratio, CropProvenance::auto()))
+                .collect(),
             ..info
         };
 
-        self.optimize(&dest, &final_info, status_line);
+        if scale == 1 {
+            return self.optimize(img, &final_info);
+        }
+
+        let mut dest = self.workspace().path_for(img)?;
+
+        if let Some(ext) = &self.format {
+            dest = dest.with_extension(ext);
+        }
+
+        let gpu = (self.config.upscaler.backend != UpscalerBackend::Lanczos)
+            .then(|| self.upscale_lock.lock().expect("upscale lock poisoned"));
+        self.config.upscaler.upscale(img, &dest, scale)?;
+        drop(gpu);
+
+        self.optimize(&dest, &final_info)
     }
 
-    pub fn optimize(&mut self, img: &PathBuf, info: &WallInfo, status_line: &str) {
-        wait_for_image(img);
+    pub fn optimize(&self, img: &PathBuf, info: &WallInfo) -> Result<(), PipelineError> {
+        let out_img = self.workspace().path_for(
+            self.format
+                .as_ref()
+                .map_or_else(|| img.clone(), |format| img.with_extension(format)),
+        )?;
 
-        print!("{status_line} Optimizing image...{}", " ".repeat(10));
-        std::io::stdout().flush().expect("could not flush stdout");
+        self.config.optimizer.optimize(img, &out_img)?;
 
-        let out_img = self
-            .format
-            .as_ref()
-            .map_or_else(|| img.clone(), |format| img.with_extension(format))
-            .with_directory("/tmp");
-
-        if let Some(ext) = out_img.extension().and_then(|ext| ext.to_str()) {
-            (match ext {
-                "jpg" | "jpeg" => optimize_jpg(img, &out_img),
-                "png" => optimize_png(img, &out_img),
-                "webp" => optimize_webp(img, &out_img),
-                _ => panic!("unsupported image format: {ext:?}"),
-            })
-            .unwrap_or_else(|_| panic!("could not optimize {}", img.display()));
-        }
+        // copy final image to wallpapers dir
+        let final_img = out_img.with_directory(&self.output);
+        self.journal.record("start", &final_img)?;
+        std::fs::copy(&out_img, &final_img)?;
 
-        // save the image
-        (WallInfo {
-            path: out_img.clone(),
+        // free up space early, as upscaled images can be large
+        self.workspace().remove(img)?;
+        self.workspace().remove(&out_img)?;
+
+        // save the metadata, after copying as sidecars are not copied along with the image
+        let mut final_info = WallInfo {
+            path: final_img.clone(),
             ..info.clone()
-        })
-        .save()
-        .unwrap_or_else(|_| panic!("could not save {}", out_img.display()));
+        };
 
-        // copy final image with metadata to wallpapers dir
-        std::fs::copy(&out_img, out_img.with_directory(&self.output))
-            .unwrap_or_else(|_| panic!("could not copy {} to wallpapers dir", out_img.display()));
+        // images without faces are cropped by detail, computed from the final image so the crops
+        // match the defaults shown in the gui
+        if final_info.faces.is_empty() {
+            let cropper = final_info.default_cropper(&self.config.crop);
+            for (ratio, geom) in &mut final_info.geometries {
+                *geom = cropper.crop(ratio);
+            }
+        }
+        self.index().save_info(&final_info)?;
+        self.journal.record("end", &final_img)?;
 
         // preview both multiple faces and no faces
         if info.faces.len() != 1 {
@@ -299,13 +493,32 @@ impl WallpaperPipeline {
             if let Some(ext) = &self.format {
                 preview_img = preview_img.with_extension(ext);
             }
-            self.to_preview.push(preview_img);
+            self.queue_preview(preview_img);
         }
+
+        Ok(())
     }
 
-    pub fn preview(self) {
-        if !self.to_preview.is_empty() {
-            run_wallfacer(self.to_preview);
+    /// finishes the run, the journal is kept if any images failed so the run can be resumed
+    pub fn preview(self, has_failures: bool) {
+        // the gui reads from the index, so it needs to be up to date before launching
+        let saved = self.index().save();
+        if let Err(e) = saved {
+            eprintln!("Unable to save wallpaper index: {e}");
+        }
+
+        if !has_failures && let Err(e) = self.journal.remove() {
+            eprintln!("Unable to remove journal: {e}");
+        }
+
+        // remove the intermediate files before the gui blocks
+        drop(self.workspace);
+
+        let mut to_preview = self.to_preview.into_inner().expect("preview lock poisoned");
+        to_preview.numeric_sort();
+
+        if !to_preview.is_empty() {
+            run_wallfacer(to_preview);
         }
     }
 }
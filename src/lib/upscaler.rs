@@ -0,0 +1,226 @@
+use image::{ImageReader, imageops::FilterType};
+use serde::{Deserialize, Serialize};
+use std::{path::Path, process::Command};
+use thiserror::Error;
+
+use super::{
+    command::{CommandError, run_command},
+    full_path,
+};
+
+/// placeholders in the command args that are replaced for each image
+const INPUT_PLACEHOLDER: &str = "{input}";
+const OUTPUT_PLACEHOLDER: &str = "{output}";
+const SCALE_PLACEHOLDER: &str = "{scale}";
+
+#[derive(Error, Debug)]
+pub enum UpscalerError {
+    #[error(transparent)]
+    Command(#[from] CommandError),
+    #[error("Unable to upscale image: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Unable to read image: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("No command configured for the command upscaler")]
+    MissingCommand,
+}
+
+pub trait Upscaler {
+    /// upscales the input image by an integer scale, writing the result to output
+    fn upscale(&self, input: &Path, output: &Path, scale: u32) -> Result<(), UpscalerError>;
+}
+
+/// <https://github.com/nihui/realcugan-ncnn-vulkan>, requires vulkan
+pub struct RealCugan {
+    /// model directory, e.g. models-se
+    pub model: Option<String>,
+    /// denoise level, -1 to 3
+    pub noise: Option<i32>,
+    /// tile size, 0 for auto
+    pub tile_size: Option<u32>,
+}
+
+impl Upscaler for RealCugan {
+    fn upscale(&self, input: &Path, output: &Path, scale: u32) -> Result<(), UpscalerError> {
+        let mut cmd = Command::new("realcugan-ncnn-vulkan");
+        cmd.arg("-i")
+            .arg(input)
+            .arg("-s")
+            .arg(scale.to_string())
+            .arg("-o")
+            .arg(output);
+
+        if let Some(model) = &self.model {
+            cmd.arg("-m").arg(full_path(model));
+        }
+        if let Some(noise) = self.noise {
+            cmd.arg("-n").arg(noise.to_string());
+        }
+        if let Some(tile_size) = self.tile_size {
+            cmd.arg("-t").arg(tile_size.to_string());
+        }
+
+        run_command(&mut cmd, "realcugan-ncnn-vulkan")?;
+        Ok(())
+    }
+}
+
+/// any upscaler command, e.g. waifu2x or realesrgan
+pub struct CommandUpscaler {
+    pub command: String,
+    /// "{input}", "{output}" and "{scale}" are replaced in each arg
+    pub args: Vec<String>,
+}
+
+impl Upscaler for CommandUpscaler {
+    fn upscale(&self, input: &Path, output: &Path, scale: u32) -> Result<(), UpscalerError> {
+        let input = input.to_string_lossy();
+        let output = output.to_string_lossy();
+        let scale = scale.to_string();
+
+        run_command(
+            Command::new(full_path(&self.command)).args(self.args.iter().map(|arg| {
+                arg.replace(INPUT_PLACEHOLDER, &input)
+                    .replace(OUTPUT_PLACEHOLDER, &output)
+                    .replace(SCALE_PLACEHOLDER, &scale)
+            })),
+            &self.command,
+        )?;
+        Ok(())
+    }
+}
+
+/// resizes using the image crate, works anywhere but doesn't add any detail
+pub struct LanczosUpscaler;
+
+impl Upscaler for LanczosUpscaler {
+    fn upscale(&self, input: &Path, output: &Path, scale: u32) -> Result<(), UpscalerError> {
+        let img = ImageReader::open(input)?.decode()?;
+
+        img.resize_exact(
+            img.width() * scale,
+            img.height() * scale,
+            FilterType::Lanczos3,
+        )
+        .save(output)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpscalerBackend {
+    #[default]
+    RealCugan,
+    Command,
+    Lanczos,
+}
+
+/// selects how wallpapers are upscaled when adding them
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpscalerConfig {
+    pub backend: UpscalerBackend,
+    /// executable for the command backend
+    pub command: Option<String>,
+    /// args for the command backend, "{input}", "{output}" and "{scale}" are replaced
+    pub args: Vec<String>,
+    /// model for the real-cugan backend
+    pub model: Option<String>,
+    /// denoise level for the real-cugan backend
+    pub noise: Option<i32>,
+    /// tile size for the real-cugan backend
+    pub tile_size: Option<u32>,
+}
+
+impl Upscaler for UpscalerConfig {
+    fn upscale(&self, input: &Path, output: &Path, scale: u32) -> Result<(), UpscalerError> {
+        match self.backend {
+            UpscalerBackend::RealCugan => RealCugan {
+                model: self.model.clone(),
+                noise: self.noise,
+                tile_size: self.tile_size,
+            }
+            .upscale(input, output, scale),
+            UpscalerBackend::Command => CommandUpscaler {
+                command: self.command.clone().ok_or(UpscalerError::MissingCommand)?,
+                args: self.args.clone(),
+            }
+            .upscale(input, output, scale),
+            UpscalerBackend::Lanczos => LanczosUpscaler.upscale(input, output, scale),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::TempWorkspace;
+    use image::RgbImage;
+
+    fn input_image(workspace: &TempWorkspace) -> std::path::PathBuf {
+        let input = workspace.dir().join("input.png");
+        RgbImage::new(4, 3)
+            .save(&input)
+            .expect("could not write image");
+        input
+    }
+
+    #[test]
+    fn test_lanczos() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let input = input_image(&workspace);
+        let output = workspace.dir().join("output.png");
+
+        LanczosUpscaler
+            .upscale(&input, &output, 2)
+            .expect("could not upscale");
+        assert_eq!(
+            image::image_dimensions(&output).expect("could not read output"),
+            (8, 6)
+        );
+
+        assert!(matches!(
+            LanczosUpscaler.upscale(&workspace.dir().join("missing.png"), &output, 2),
+            Err(UpscalerError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_command() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let input = input_image(&workspace);
+        let output = workspace.dir().join("output.png");
+
+        let upscaler = CommandUpscaler {
+            command: "cp".to_string(),
+            args: vec![
+                INPUT_PLACEHOLDER.to_string(),
+                OUTPUT_PLACEHOLDER.to_string(),
+            ],
+        };
+        upscaler
+            .upscale(&input, &output, 2)
+            .expect("could not run command");
+        assert!(output.exists());
+
+        let failing = CommandUpscaler {
+            command: "false".to_string(),
+            args: Vec::new(),
+        };
+        assert!(matches!(
+            failing.upscale(&input, &output, 2),
+            Err(UpscalerError::Command(CommandError::Failed(..)))
+        ));
+
+        let missing = UpscalerConfig {
+            backend: UpscalerBackend::Command,
+            ..UpscalerConfig::default()
+        };
+        assert!(matches!(
+            missing.upscale(&input, &output, 2),
+            Err(UpscalerError::MissingCommand)
+        ));
+    }
+}
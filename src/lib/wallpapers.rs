@@ -1,14 +1,39 @@
 use indexmap::IndexMap;
 use itertools::Itertools;
-use rexiv2::Metadata;
 use std::path::{Path, PathBuf};
+use thiserror::Error;
 
 use super::{
     aspect_ratio::AspectRatio,
+    config::Config,
+    crop_strategy::CropConfig,
     cropper::{Cropper, Direction},
+    energy::LazyEnergy,
     geometry::Geometry,
+    layout::SpanLayout,
+    metadata::{CropProvenance, MetadataStore, WallMetadata},
 };
 
+#[derive(Error, Debug)]
+pub enum WallInfoError {
+    #[error("Unable to read file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Unable to get image dimensions: {0}")]
+    ImageDimensions(#[from] image::ImageError),
+    #[error("Unable to read metadata: {0}")]
+    Exiv2(#[from] rexiv2::Rexiv2Error),
+    #[error("Unable to read sidecar: {0}")]
+    Sidecar(#[from] serde_json::Error),
+    #[error("Invalid face: {0}")]
+    InvalidFace(String),
+    #[error("Invalid crop: {0}")]
+    InvalidCrop(String),
+    #[error("Invalid aspect ratio in crop tag: {0}")]
+    InvalidAspect(String),
+    #[error("Invalid crop provenance: {0}")]
+    InvalidProvenance(String),
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct WallInfo {
     pub path: PathBuf,
@@ -17,167 +42,285 @@ pub struct WallInfo {
     pub faces: Vec<Geometry>,
     pub scale: Option<u32>,
     pub geometries: IndexMap<AspectRatio, Geometry>,
+    /// where each crop came from, missing for crops saved before it was tracked
+    pub provenance: IndexMap<AspectRatio, CropProvenance>,
+    /// crops spanning a multi-monitor layout, keyed by the layout name
+    pub spans: IndexMap<String, Geometry>,
 }
 
 impl WallInfo {
-    pub fn new_from_file<P>(img: P) -> Self
+    pub fn new_from_file<P>(img: P, store: &impl MetadataStore) -> Result<Self, WallInfoError>
     where
         P: AsRef<Path> + std::fmt::Debug,
     {
-        let (width, height) =
-            image::image_dimensions(&img).expect("could not get image dimensions");
-
-        let meta = Metadata::new_from_path(img.as_ref()).expect("could not init new metadata");
-
-        let mut faces = Vec::new();
-        let mut scale = None;
-        let mut crops = IndexMap::new();
-
-        for tag in meta.get_xmp_tags().expect("unable to read xmp tags") {
-            match tag.as_str() {
-                "Xmp.wallfacer.faces" => {
-                    let face_str = meta.get_tag_string(&tag).expect("could not get faces tag");
-
-                    // empty faces are written as "[]" as rexiv2 seems to return the value of
-                    // the next Xmp field, which is wrong
-                    if face_str != "[]" {
-                        faces = face_str
-                            .split(',')
-                            .map(|face| {
-                                face.try_into().unwrap_or_else(|_| {
-                                    panic!("could not convert face {face} into string")
-                                })
-                            })
-                            .collect();
-                    }
-                }
-                "Xmp.wallfacer.scale" => {
-                    scale = meta
-                        .get_tag_string(&tag)
-                        .expect("could not get scale tag")
-                        .parse::<u32>()
-                        .ok();
-                }
-                tag if tag.starts_with("Xmp.wallfacer.crop.") => {
-                    let aspect = tag
-                        .strip_prefix("Xmp.wallfacer.crop.")
-                        .expect("could not strip cropdata prefix");
-                    let aspect: AspectRatio = aspect
-                        .try_into()
-                        .unwrap_or_else(|_| panic!("could not parse aspect ratio {aspect}"));
-                    let geom_str = meta.get_tag_string(tag).expect("could not get crop tag");
-                    let geoms: Geometry = geom_str
-                        .as_str()
-                        .try_into()
-                        .unwrap_or_else(|_| panic!("could not parse crop {geom_str}"));
-
-                    crops.insert(aspect, geoms);
-                }
-                _ => {}
-            }
-        }
+        // surface missing / unreadable files before the more opaque image and exiv2 errors
+        std::fs::metadata(img.as_ref())?;
+
+        let (width, height) = image::image_dimensions(&img)?;
+
+        let WallMetadata {
+            faces,
+            scale,
+            geometries,
+            provenance,
+            spans,
+        } = store.read(img.as_ref())?;
 
-        Self {
+        Ok(Self {
             width,
             height,
             path: img.as_ref().to_path_buf(),
             scale,
             faces,
-            geometries: crops,
-        }
+            geometries,
+            provenance,
+            spans,
+        })
     }
 
-    pub fn save(&self) -> rexiv2::Result<()> {
-        let prev_modified = std::fs::metadata(&self.path)
-            .and_then(|metadata| metadata.modified())
-            .ok();
-
-        let meta = Metadata::new_from_path(&self.path)?;
-
-        // set face metadata
-        let face_strings = if self.faces.is_empty() {
-            "[]".to_string()
-        } else {
-            self.faces
-                .iter()
-                .map(std::string::ToString::to_string)
-                .join(",")
-        };
-
-        meta.set_tag_string("Xmp.wallfacer.faces", &face_strings)?;
-
-        if let Some(scale) = self.scale {
-            meta.set_tag_string("Xmp.wallfacer.scale", &scale.to_string())?;
+    /// the wallfacer data to be stored for the wallpaper
+    pub fn metadata(&self) -> WallMetadata {
+        WallMetadata {
+            faces: self.faces.clone(),
+            scale: self.scale,
+            geometries: self.geometries.clone(),
+            provenance: self.provenance.clone(),
+            spans: self.spans.clone(),
         }
+    }
 
-        // set crop data
-        for (aspect, geom) in &self.geometries {
-            let crop_key = format!("Xmp.wallfacer.crop.{}", aspect);
-            meta.set_tag_string(&crop_key, &geom.to_string())?;
-        }
+    pub fn save(&self, store: &impl MetadataStore) -> Result<(), WallInfoError> {
+        store.write(&self.path, &self.metadata())
+    }
 
-        meta.save_to_file(&self.path)?;
+    /// smallest scale that meets the minimum dimensions and where the crop for each resolution is
+    /// at least as large as its monitor, falling back to the largest scale meeting the minimum
+    pub fn get_target_scale(&self, cfg: &Config) -> Option<u32> {
+        let (min_width, min_height) = cfg.min_dimensions(self.width, self.height);
+        let meets_min =
+            |scale: &u32| self.width * scale >= min_width && self.height * scale >= min_height;
 
-        // reset the modified time to maintain sort order
-        if let Some(prev_modified) = prev_modified {
-            std::fs::OpenOptions::new()
-                .write(true)
-                .open(&self.path)
-                .and_then(|f| f.set_modified(prev_modified))
-                .ok();
-        }
+        let covers_monitors = |scale: &u32| {
+            let cropper = Cropper::new(&[], self.width * scale, self.height * scale);
 
-        Ok(())
-    }
+            cfg.resolutions.iter().all(|res| {
+                let (crop_w, crop_h, _) = cropper.crop_rect(&res.resolution);
+                crop_w >= res.width && crop_h >= res.height
+            })
+        };
 
-    pub fn get_target_scale(&self, min_width: u32, min_height: u32) -> Option<u32> {
-        (1..=4).find(|&scale| self.width * scale >= min_width && self.height * scale >= min_height)
+        (1..=4)
+            .find(|scale| meets_min(scale) && covers_monitors(scale))
+            .or_else(|| (1..=4).rev().find(meets_min))
     }
 
     pub fn dimensions_f64(&self) -> (f64, f64) {
         (f64::from(self.width), f64::from(self.height))
     }
 
-    pub fn has_metadata<P>(img: P) -> bool
+    pub fn has_metadata<P>(img: P, store: &impl MetadataStore) -> bool
     where
         P: AsRef<Path>,
     {
-        Metadata::new_from_path(img.as_ref())
-            .and_then(|meta| meta.get_tag_string("Xmp.wallfacer.faces"))
-            .is_ok()
+        store.has_metadata(img.as_ref())
     }
 
     pub fn ratio(&self) -> f64 {
         f64::from(self.width) / f64::from(self.height)
     }
 
-    pub const fn direction(&self, g: &Geometry) -> Direction {
-        if self.height == g.h {
-            Direction::X
-        } else {
-            Direction::Y
-        }
+    /// axis the crop can be moved along, none if the crop is zoomed in and can move along both
+    pub const fn direction(&self, g: &Geometry) -> Option<Direction> {
+        Direction::of_crop(self.width, self.height, g)
     }
 
     pub fn cropper(&self) -> Cropper {
         Cropper::new(&self.faces, self.width, self.height)
     }
 
-    pub fn get_geometry(&self, ratio: &AspectRatio) -> Geometry {
+    /// cropper using the configured strategy, the energy of the image is only computed if a crop
+    /// without faces is needed
+    pub fn default_cropper(&self, cfg: &CropConfig) -> Cropper {
+        self.cropper()
+            .with_strategy(cfg.strategy)
+            .with_energy(LazyEnergy::new(&self.path))
+    }
+
+    /// stored crop for the ratio, or the default crop of the cropper if there is none
+    pub fn get_geometry(&self, ratio: &AspectRatio, cropper: &Cropper) -> Geometry {
         self.geometries
             .get(ratio)
-            .map_or_else(|| self.cropper().crop(ratio), std::clone::Clone::clone)
+            .map_or_else(|| cropper.crop(ratio), std::clone::Clone::clone)
     }
 
     pub fn set_geometry(&mut self, ratio: &AspectRatio, new_geom: &Geometry) {
         self.geometries.insert(ratio.clone(), new_geom.clone());
     }
 
+    /// sets a default crop computed by the current cropper
+    pub fn set_default_geometry(&mut self, ratio: &AspectRatio, new_geom: &Geometry) {
+        self.set_geometry(ratio, new_geom);
+        self.provenance
+            .insert(ratio.clone(), CropProvenance::auto());
+    }
+
+    /// the crop was computed by a cropper rather than edited, crops saved before the provenance
+    /// was tracked are only considered automatic if they still match the default crop
+    pub fn is_auto_crop(&self, ratio: &AspectRatio) -> bool {
+        match self.provenance.get(ratio) {
+            Some(CropProvenance::Auto(_)) => true,
+            Some(CropProvenance::User) => false,
+            // missing crops are automatic, stored crops without a provenance predate the crop
+            // strategies and edge energy, so they are compared to the crop using only the faces,
+            // which also avoids decoding the image
+            None => self
+                .geometries
+                .get(ratio)
+                .is_none_or(|geom| *geom == self.cropper().crop(ratio)),
+        }
+    }
+
+    /// updates the provenance of the crops that differ from the source, crops matching the
+    /// default crop are automatic and the rest were edited by the user
+    pub fn update_provenance(&mut self, source: &Self, cropper: &Cropper) {
+        for (ratio, geom) in &self.geometries {
+            if source.geometries.get(ratio) == Some(geom) {
+                continue;
+            }
+
+            let provenance = if *geom == cropper.crop(ratio) {
+                CropProvenance::auto()
+            } else {
+                CropProvenance::User
+            };
+            self.provenance.insert(ratio.clone(), provenance);
+        }
+    }
+
+    /// stored crop for the layout, or the default crop of the cropper if there is none
+    pub fn get_span(&self, layout: &SpanLayout, cropper: &Cropper) -> Geometry {
+        self.spans.get(&layout.name).map_or_else(
+            || cropper.crop(&layout.aspect_ratio()),
+            std::clone::Clone::clone,
+        )
+    }
+
+    pub fn set_span(&mut self, layout: &SpanLayout, new_geom: &Geometry) {
+        self.spans.insert(layout.name.clone(), new_geom.clone());
+    }
+
+    /// spans don't track their provenance, so like the crops saved before it was tracked, they are
+    /// only considered automatic if they are missing or still match the default crop
+    pub fn is_auto_span(&self, layout: &SpanLayout, cropper: &Cropper) -> bool {
+        self.spans
+            .get(&layout.name)
+            .is_none_or(|geom| *geom == cropper.crop(&layout.aspect_ratio()))
+    }
+
+    /// replaces the faces, the automatic crops and spans of the cropper are recomputed with the
+    /// new faces while the edited ones are kept
+    pub fn update_faces(
+        &mut self,
+        faces: Vec<Geometry>,
+        cropper: &Cropper,
+        layouts: &[SpanLayout],
+    ) {
+        let new_cropper = Cropper::new(&faces, self.width, self.height)
+            .with_strategy(cropper.strategy)
+            .with_energy(cropper.energy.clone());
+
+        let auto_ratios = self
+            .geometries
+            .keys()
+            .filter(|ratio| self.is_auto_crop(ratio))
+            .cloned()
+            .collect_vec();
+        for ratio in auto_ratios {
+            self.set_default_geometry(&ratio, &new_cropper.crop(&ratio));
+        }
+
+        // missing spans already fall back to the default crop of the new faces
+        for layout in layouts {
+            if self.spans.contains_key(&layout.name) && self.is_auto_span(layout, cropper) {
+                self.set_span(layout, &new_cropper.crop(&layout.aspect_ratio()));
+            }
+        }
+
+        self.faces = faces;
+    }
+
+    /// none of the crops were edited by the user, even if they were computed by an older cropper
     pub fn is_default_crops(&self, resolutions: &[AspectRatio]) -> bool {
-        let cropper = self.cropper();
+        resolutions.iter().all(|ratio| self.is_auto_crop(ratio))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::SpanMonitor;
+
+    fn monitor(name: &str, x: u32) -> SpanMonitor {
+        SpanMonitor {
+            name: name.into(),
+            width: 1920,
+            height: 1080,
+            x,
+            y: 0,
+        }
+    }
+
+    #[test]
+    fn test_update_faces() {
+        let hd = AspectRatio::new(1920, 1080);
+        let vertical = AspectRatio::new(1080, 1920);
+        let square = AspectRatio::new(1, 1);
+        let layout = SpanLayout {
+            name: "dual".into(),
+            description: None,
+            bezel: 0,
+            monitors: vec![monitor("left", 0), monitor("right", 1920)],
+        };
+
+        let mut info = WallInfo {
+            width: 4000,
+            height: 2000,
+            ..WallInfo::default()
+        };
+        let cropper = info.cropper();
+        let custom = Geometry {
+            x: 0,
+            y: 0,
+            w: 2000,
+            h: 2000,
+        };
+
+        info.set_default_geometry(&hd, &cropper.crop(&hd));
+        // edited back to the default crop, but still chosen by the user
+        info.set_geometry(&vertical, &cropper.crop(&vertical));
+        info.provenance
+            .insert(vertical.clone(), CropProvenance::User);
+        // saved before the provenance was tracked
+        info.set_geometry(&square, &custom);
+        info.set_span(&layout, &cropper.crop(&layout.aspect_ratio()));
+
+        let faces = vec![Geometry {
+            x: 3800,
+            y: 0,
+            w: 100,
+            h: 100,
+        }];
+        let new_cropper = Cropper::new(&faces, info.width, info.height);
+        info.update_faces(faces, &cropper, std::slice::from_ref(&layout));
 
-        resolutions
-            .iter()
-            .all(|ratio| self.get_geometry(ratio) == cropper.crop(ratio))
+        assert_eq!(info.geometries[&hd], new_cropper.crop(&hd));
+        assert_ne!(info.geometries[&hd], cropper.crop(&hd));
+        assert_eq!(info.geometries[&vertical], cropper.crop(&vertical));
+        assert_eq!(info.provenance[&vertical], CropProvenance::User);
+        assert_eq!(info.geometries[&square], custom);
+        assert_eq!(
+            info.spans[&layout.name],
+            new_cropper.crop(&layout.aspect_ratio())
+        );
     }
 }
@@ -0,0 +1,194 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const PREFIX: &str = "wallfacer-";
+
+/// a temporary directory for the intermediate files of a single run, so concurrent runs and inputs
+/// with the same filename never clobber each other, removed along with its contents when dropped
+pub struct TempWorkspace {
+    dir: PathBuf,
+    counter: AtomicUsize,
+}
+
+impl TempWorkspace {
+    /// creates a uniquely named directory within the system temp directory, which honors TMPDIR
+    pub fn new(name: &str) -> std::io::Result<Self> {
+        Self::remove_stale();
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+
+        let mut attempt = 0;
+        loop {
+            let dir = std::env::temp_dir().join(format!(
+                "{PREFIX}{name}-{}-{nanos}-{attempt}",
+                std::process::id()
+            ));
+
+            match std::fs::create_dir(&dir) {
+                Ok(()) => {
+                    return Ok(Self {
+                        dir,
+                        counter: AtomicUsize::new(0),
+                    });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// pid of the process that created the workspace, parsed from its directory name
+    fn owner_pid(dirname: &str) -> Option<u32> {
+        let mut parts = dirname.strip_prefix(PREFIX)?.rsplitn(4, '-');
+        let attempt = parts.next()?;
+        let nanos = parts.next()?;
+        let pid = parts.next()?;
+        parts.next()?;
+
+        if attempt.parse::<usize>().is_err() || nanos.parse::<u32>().is_err() {
+            return None;
+        }
+        pid.parse().ok()
+    }
+
+    /// removes the workspaces of runs that were killed or aborted on a panic, as Drop never ran
+    fn remove_stale() {
+        // without procfs there is no way to tell if the owner is still running
+        if !Path::new("/proc/self").exists() {
+            return;
+        }
+
+        let Ok(entries) = std::fs::read_dir(std::env::temp_dir()) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(Self::owner_pid) else {
+                continue;
+            };
+
+            let path = entry.path();
+            if !path.is_dir() || Path::new("/proc").join(pid.to_string()).exists() {
+                continue;
+            }
+
+            if let Err(e) = std::fs::remove_dir_all(&path) {
+                eprintln!("Unable to remove {}: {e}", path.display());
+            }
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// unique path for an intermediate file, the filename is kept so it can still be used to
+    /// derive the final path
+    pub fn path_for<P>(&self, path: P) -> std::io::Result<PathBuf>
+    where
+        P: AsRef<Path>,
+    {
+        let subdir = self
+            .dir
+            .join(self.counter.fetch_add(1, Ordering::Relaxed).to_string());
+        std::fs::create_dir(&subdir)?;
+
+        Ok(subdir.join(
+            path.as_ref().file_name().unwrap_or_else(|| {
+                panic!("could not get filename for {}", path.as_ref().display())
+            }),
+        ))
+    }
+
+    /// removes an intermediate file early once it is no longer needed, paths outside of the
+    /// workspace are left alone
+    pub fn remove<P>(&self, path: P) -> std::io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        match path.as_ref().parent() {
+            Some(subdir) if subdir.starts_with(&self.dir) && subdir != self.dir => {
+                std::fs::remove_dir_all(subdir)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for TempWorkspace {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            eprintln!("Unable to remove {}: {e}", self.dir.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_paths() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let other = TempWorkspace::new("test").expect("could not create workspace");
+        assert_ne!(workspace.dir(), other.dir());
+
+        let first = workspace
+            .path_for("/a/img.png")
+            .expect("could not create path");
+        let second = workspace
+            .path_for("/b/img.png")
+            .expect("could not create path");
+
+        assert_ne!(first, second);
+        assert_eq!(first.file_name(), second.file_name());
+        assert!(first.starts_with(workspace.dir()));
+    }
+
+    #[test]
+    fn test_cleanup() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let dir = workspace.dir().to_path_buf();
+        let path = workspace
+            .path_for("img.png")
+            .expect("could not create path");
+        std::fs::write(path, "").expect("could not write file");
+
+        drop(workspace);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_owner_pid() {
+        assert_eq!(
+            TempWorkspace::owner_pid("wallfacer-add-123-456-0"),
+            Some(123)
+        );
+        assert_eq!(
+            TempWorkspace::owner_pid("wallfacer-a-b-123-456-1"),
+            Some(123)
+        );
+        assert_eq!(
+            TempWorkspace::owner_pid("wallfacer-test-fixture.json"),
+            None
+        );
+        assert_eq!(TempWorkspace::owner_pid("other-add-123-456-0"), None);
+    }
+
+    #[test]
+    fn test_remove_stale() {
+        // larger than any pid linux hands out
+        let stale = std::env::temp_dir().join(format!("{PREFIX}test-{}-0-0", u32::MAX));
+        std::fs::create_dir_all(stale.join("0")).expect("could not create stale workspace");
+
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        assert!(!stale.exists());
+        assert!(workspace.dir().exists());
+    }
+}
@@ -6,9 +6,15 @@ use dioxus::prelude::*;
 use screens::app::App;
 use wallfacer::cli::{Commands, ShellCompletion, WallfacerArgs};
 
-pub mod add_resolution;
 pub mod add_wallpapers;
+pub mod check;
 pub mod components;
+pub mod detect;
+pub mod export;
+pub mod index;
+pub mod migrate;
+pub mod recrop;
+pub mod resolution;
 pub mod screens;
 pub mod state;
 pub mod trimmer;
@@ -46,7 +52,13 @@ fn main() {
 
     match all_args.command {
         Some(Commands::Add(args)) => add_wallpapers::main(all_args.config, &args),
-        Some(Commands::AddResolution(args)) => add_resolution::main(all_args.config, &args),
+        Some(Commands::Resolution(args)) => resolution::main(all_args.config, &args),
+        Some(Commands::Check(args)) => check::main(all_args.config, &args),
+        Some(Commands::Detect(args)) => detect::main(all_args.config, &args),
+        Some(Commands::Migrate(args)) => migrate::main(all_args.config, &args),
+        Some(Commands::Recrop(args)) => recrop::main(all_args.config, &args),
+        Some(Commands::Index(args)) => index::main(all_args.config, &args),
+        Some(Commands::Export(args)) => export::main(all_args.config, &args),
         Some(Commands::Trim(args)) => trimmer::main(&args),
         // default to gui
         Some(Commands::Gui(_)) => {
@@ -65,7 +77,7 @@ fn main() {
         }
         _ => {
             eprintln!(
-                "Unknown / invlaid subcommand, valid subcommands are: add, resolution, gui, trim"
+                "Unknown / invlaid subcommand, valid subcommands are: add, resolution, export, check, detect, migrate, recrop, index, gui, trim"
             );
             std::process::exit(1);
         }
@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+
+use wallfacer::{
+    PathBufVecExt,
+    cli::{MetadataTarget, MigrateArgs},
+    config::Config,
+    metadata::{MetadataBackend, MetadataConfig, MetadataStore},
+    report_failures,
+};
+
+/// moves the data to the new backend, returns false if there was nothing to migrate
+fn migrate_wallpaper(
+    path: &Path,
+    from: &MetadataConfig,
+    to: &MetadataConfig,
+) -> Result<bool, String> {
+    if !from.has_metadata(path) {
+        return Ok(false);
+    }
+
+    let data = from.read(path).map_err(|e| e.to_string())?;
+    to.write(path, &data).map_err(|e| e.to_string())?;
+
+    // verify the data was written losslessly before removing it from the previous backend
+    if to.read(path).map_err(|e| e.to_string())? != data {
+        return Err(format!(
+            "data written to {} backend does not match",
+            to.backend
+        ));
+    }
+
+    from.remove(path).map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+pub fn main(config_path: Option<PathBuf>, args: &MigrateArgs) {
+    let cfg = Config::new(config_path).expect("failed to load config");
+    let mut all_files = args.paths.filter_wallpapers();
+    all_files.numeric_sort();
+
+    let (from, to) = match args.to {
+        MetadataTarget::Embedded => (MetadataBackend::Sidecar, MetadataBackend::Embedded),
+        MetadataTarget::Sidecar => (MetadataBackend::Embedded, MetadataBackend::Sidecar),
+    };
+    let from = cfg.metadata.with_backend(from);
+    let to = cfg.metadata.with_backend(to);
+
+    let mut migrated = 0;
+    let mut failed = Vec::new();
+    for path in &all_files {
+        match migrate_wallpaper(path, &from, &to) {
+            Ok(true) => {
+                println!("Migrated: {}", path.display());
+                migrated += 1;
+            }
+            Ok(false) => {}
+            Err(e) => failed.push((path, e)),
+        }
+    }
+
+    println!(
+        "\n{migrated} / {} wallpapers migrated to {to_backend}",
+        all_files.len(),
+        to_backend = to.backend
+    );
+
+    report_failures("migrated", &failed, all_files.len());
+
+    if cfg.metadata.backend != to.backend {
+        println!(
+            "Set the following in wallfacer.toml to use the migrated data:\n\n[metadata]\nbackend = \"{}\"",
+            to.backend
+        );
+    }
+}
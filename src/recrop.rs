@@ -0,0 +1,251 @@
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use wallfacer::{
+    PathBufVecExt, cli::RecropArgs, config::Config, cropper::CROPPER_VERSION, geometry::Geometry,
+    index::WallIndex, metadata::CropProvenance, report_failures, wallpapers::WallInfo,
+};
+
+/// describes how far a crop moved
+fn describe_move(name: &str, old: &Geometry, new: &Geometry) -> String {
+    let dx = i64::from(new.x) - i64::from(old.x);
+    let dy = i64::from(new.y) - i64::from(old.y);
+    let distance = (dx as f64).hypot(dy as f64);
+
+    let resized = if (old.w, old.h) == (new.w, new.h) {
+        String::new()
+    } else {
+        format!(", resized {}x{} -> {}x{}", old.w, old.h, new.w, new.h)
+    };
+
+    format!("    {name}: moved {dx:+}, {dy:+} ({distance:.0}px){resized}")
+}
+
+/// refreshes the automatic crops computed by an older cropper, or every automatic crop if `all`
+/// is set, returns the descriptions of the crops that moved and if any provenance needs to be
+/// saved
+///
+/// span crops are only stored once they are edited, so there are no automatic spans to refresh
+fn recrop_wallpaper(info: &mut WallInfo, cfg: &Config, all: bool) -> (Vec<String>, bool) {
+    let cropper = info.default_cropper(&cfg.crop);
+    let mut moved = Vec::new();
+    let mut changed = false;
+
+    for res in &cfg.resolutions {
+        let ratio = &res.resolution;
+        let stale = match info.provenance.get(ratio) {
+            Some(CropProvenance::Auto(version)) => all || *version < CROPPER_VERSION,
+            Some(CropProvenance::User) => false,
+            // saved before the provenance was tracked, only refreshed if it is a default crop
+            None => info.is_auto_crop(ratio),
+        };
+
+        if !stale {
+            continue;
+        }
+
+        let old_geom = info.get_geometry(ratio, &cropper);
+        let new_geom = cropper.crop(ratio);
+        if old_geom != new_geom {
+            moved.push(describe_move(&res.name, &old_geom, &new_geom));
+        }
+
+        if old_geom != new_geom
+            || info.geometries.get(ratio).is_none()
+            || info.provenance.get(ratio) != Some(&CropProvenance::auto())
+        {
+            info.set_default_geometry(ratio, &new_geom);
+            changed = true;
+        }
+    }
+
+    (moved, changed)
+}
+
+pub fn main(config_path: Option<PathBuf>, args: &RecropArgs) {
+    let cfg = Config::new(config_path).expect("failed to load config");
+    let mut all_files = args.paths.filter_wallpapers();
+    all_files.numeric_sort();
+
+    let mut index = WallIndex::load(&cfg.metadata);
+    let mut moved_count = 0;
+    let mut failed = Vec::new();
+
+    for path in &all_files {
+        let mut info = match index.get(path) {
+            Ok(info) => info,
+            Err(e) => {
+                failed.push((path, e));
+                continue;
+            }
+        };
+
+        let (moved, changed) = recrop_wallpaper(&mut info, &cfg, args.all);
+
+        if !moved.is_empty() {
+            moved_count += 1;
+            println!("{}", path.display());
+            println!("{}", moved.iter().join("\n"));
+        }
+
+        if changed
+            && !args.dry_run
+            && let Err(e) = index.save_info(&info)
+        {
+            failed.push((path, e));
+        }
+    }
+
+    println!(
+        "\n{moved_count} / {} wallpapers {} crops moved",
+        all_files.len(),
+        if args.dry_run { "would have" } else { "had" }
+    );
+
+    report_failures("recropped", &failed, all_files.len());
+
+    index.save().unwrap_or_else(|e| {
+        eprintln!("Unable to save wallpaper index: {e}");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use wallfacer::{
+        aspect_ratio::AspectRatio, config::ConfigResolution, workspace::TempWorkspace,
+    };
+
+    fn config() -> Config {
+        Config {
+            resolutions: vec![
+                ConfigResolution::new("HD".to_string(), None, 1920, 1080),
+                ConfigResolution::new("Vertical".to_string(), None, 1080, 1920),
+            ],
+            ..Config::default()
+        }
+    }
+
+    fn wall(provenance: Option<CropProvenance>) -> WallInfo {
+        let ratio = AspectRatio::new(1920, 1080);
+        let mut info = WallInfo {
+            width: 4000,
+            height: 2000,
+            ..WallInfo::default()
+        };
+
+        // moved away from the default crop
+        info.geometries.insert(
+            ratio.clone(),
+            Geometry {
+                x: 0,
+                y: 0,
+                w: 3552,
+                h: 1998,
+            },
+        );
+        if let Some(provenance) = provenance {
+            info.provenance.insert(ratio, provenance);
+        }
+        info
+    }
+
+    #[test]
+    fn test_recrop_stale() {
+        let cfg = config();
+        let ratio = AspectRatio::new(1920, 1080);
+        let mut info = wall(Some(CropProvenance::Auto(CROPPER_VERSION - 1)));
+        let default_geom = info.default_cropper(&cfg.crop).crop(&ratio);
+
+        let (moved, changed) = recrop_wallpaper(&mut info, &cfg, false);
+        assert!(changed);
+        assert_eq!(moved.len(), 1);
+        assert_eq!(info.geometries[&ratio], default_geom);
+        assert_eq!(info.provenance.get(&ratio), Some(&CropProvenance::auto()));
+    }
+
+    #[test]
+    fn test_recrop_current() {
+        let cfg = config();
+        let ratio = AspectRatio::new(1920, 1080);
+        let mut info = wall(Some(CropProvenance::auto()));
+        let geom = info.geometries[&ratio].clone();
+
+        // crops from the current cropper are only refreshed with --all
+        let (moved, _) = recrop_wallpaper(&mut info, &cfg, false);
+        assert!(moved.is_empty());
+        assert_eq!(info.geometries[&ratio], geom);
+
+        let (moved, changed) = recrop_wallpaper(&mut info, &cfg, true);
+        assert!(changed);
+        assert_eq!(moved.len(), 1);
+    }
+
+    #[test]
+    fn test_recrop_user() {
+        let cfg = config();
+        let ratio = AspectRatio::new(1920, 1080);
+
+        // user crops and untracked crops that differ from the default are left alone
+        for provenance in [Some(CropProvenance::User), None] {
+            let mut info = wall(provenance);
+            let geom = info.geometries[&ratio].clone();
+
+            let (moved, _) = recrop_wallpaper(&mut info, &cfg, true);
+            assert!(moved.is_empty());
+            assert_eq!(info.geometries[&ratio], geom);
+            assert_eq!(info.provenance.get(&ratio), provenance.as_ref());
+        }
+    }
+
+    #[test]
+    fn test_recrop_untracked() {
+        let cfg = config();
+        let ratio = AspectRatio::new(1080, 1920);
+        let mut info = wall(None);
+
+        // missing crops are filled in with the default crop and tracked
+        let (moved, changed) = recrop_wallpaper(&mut info, &cfg, false);
+        assert!(changed);
+        assert!(moved.is_empty());
+        assert!(info.geometries.contains_key(&ratio));
+        assert_eq!(info.provenance.get(&ratio), Some(&CropProvenance::auto()));
+    }
+
+    #[test]
+    fn test_recrop_energy() {
+        let workspace = TempWorkspace::new("test").expect("could not create workspace");
+        let cfg = config();
+        let ratio = AspectRatio::new(1080, 1920);
+
+        // a flat image with all of its detail in a striped band on the left
+        let path = workspace.dir().join("energy.png");
+        RgbImage::from_fn(400, 200, |x, y| {
+            if x < 60 && (x + y) % 2 == 0 {
+                Rgb([255, 255, 255])
+            } else {
+                Rgb([0, 0, 0])
+            }
+        })
+        .save(&path)
+        .expect("could not write image");
+
+        let mut info = WallInfo {
+            path,
+            width: 400,
+            height: 200,
+            ..WallInfo::default()
+        };
+
+        // the missing crop is filled in with the energy crop instead of the centered crop
+        let expected = info.default_cropper(&cfg.crop).crop(&ratio);
+        assert_ne!(expected, info.cropper().crop(&ratio));
+
+        let (moved, changed) = recrop_wallpaper(&mut info, &cfg, false);
+        assert!(changed);
+        assert!(moved.is_empty());
+        assert_eq!(info.geometries[&ratio], expected);
+        assert_eq!(info.provenance.get(&ratio), Some(&CropProvenance::auto()));
+    }
+}
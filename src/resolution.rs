@@ -0,0 +1,334 @@
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use wallfacer::{
+    PathBufVecExt,
+    aspect_ratio::{AspectRatio, parse_dimensions},
+    cli::{
+        AddResolutionArgs, ListResolutionArgs, RemoveResolutionArgs, RenameResolutionArgs,
+        ResolutionArgs, ResolutionCommands,
+    },
+    config::{Config, ConfigResolution},
+    crop_transfer::transfer_crop,
+    filter_images,
+    geometry::Geometry,
+    index::WallIndex,
+    metadata::CropProvenance,
+    report_failures, run_wallfacer,
+    wallpapers::WallInfo,
+};
+
+/// adds and saves the new crop geometry
+pub fn add_geometry(
+    info: &mut WallInfo,
+    aspect: &AspectRatio,
+    geom: &Geometry,
+    provenance: CropProvenance,
+    index: &mut WallIndex,
+) {
+    info.geometries.insert(aspect.clone(), geom.clone());
+    info.provenance.insert(aspect.clone(), provenance);
+    index
+        .save_info(info)
+        .unwrap_or_else(|_| panic!("could not save {}", info.path.display()));
+}
+
+fn add(mut cfg: Config, config_path: Option<PathBuf>, args: &AddResolutionArgs) {
+    // the following checks shouldn't ever trigger as clap shouldn't allow it
+    let (new_width, new_height) = parse_dimensions(&args.resolution)
+        .unwrap_or_else(|_| panic!("invalid aspect ratio: {} into string", args.resolution));
+    let new_res = AspectRatio::new(new_width, new_height);
+
+    // resolutions are looked up by name or dimensions, so both need to be unique
+    if let Some(existing) = cfg
+        .resolutions
+        .iter()
+        .find(|res| res.name == args.name || (res.width, res.height) == (new_width, new_height))
+    {
+        eprintln!(
+            "Resolution {} ({}x{}) already exists.",
+            existing.name, existing.width, existing.height
+        );
+        std::process::exit(1);
+    }
+
+    // monitors with the same aspect ratio share their crops, so there are none to add
+    let has_crops = cfg.resolutions.iter().any(|res| res.resolution == new_res);
+
+    // finds the closest resolution to an existing one
+    let closest_res = cfg
+        .resolutions
+        .iter()
+        .min_by(|res1, res2| {
+            let diff1 = (f64::from(&res1.resolution) - f64::from(&new_res)).abs();
+            let diff2 = (f64::from(&res2.resolution) - f64::from(&new_res)).abs();
+            // ignore if aspect ratio already exists in config
+            diff1
+                .partial_cmp(&diff2)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|res| res.resolution.clone());
+
+    // save the updated config
+    cfg.resolutions.push(ConfigResolution::new(
+        args.name.clone(),
+        Some(args.name.clone()),
+        new_width,
+        new_height,
+    ));
+    cfg.save(config_path).unwrap_or_else(|_| {
+        eprintln!("Unable to add resolution to existing config, please do so manually.");
+        std::process::exit(1);
+    });
+
+    if has_crops {
+        println!(
+            "Resolution {} uses the existing crops for {new_res}.",
+            args.name
+        );
+        return;
+    }
+
+    let mut index = WallIndex::load(&cfg.metadata);
+    let mut to_process: Vec<PathBuf> = Vec::new();
+    let mut failed = Vec::new();
+
+    let mut all_files = filter_images(&args.output).collect_vec();
+    all_files.numeric_sort();
+
+    for path in &all_files {
+        println!("Processing {}", path.display());
+        let mut info = match index.get(path) {
+            Ok(info) => info,
+            Err(e) => {
+                failed.push((path, e));
+                continue;
+            }
+        };
+
+        // keep crops the user already edited, e.g. left over from a resolution that was removed
+        if info.provenance.get(&new_res) == Some(&CropProvenance::User) {
+            continue;
+        }
+
+        let cropper = info.default_cropper(&cfg.crop);
+
+        match &closest_res {
+            // the closest crop was edited, transfer the edit to the new crop
+            Some(closest) if !info.is_auto_crop(closest) => {
+                let new_geom =
+                    transfer_crop(&cropper, &info.get_geometry(closest, &cropper), &new_res);
+                add_geometry(
+                    &mut info,
+                    &new_res,
+                    &new_geom,
+                    CropProvenance::User,
+                    &mut index,
+                );
+                to_process.push(path.clone());
+            }
+            // the closest crop was not changed, just use the default
+            _ => add_geometry(
+                &mut info,
+                &new_res,
+                &cropper.crop(&new_res),
+                CropProvenance::auto(),
+                &mut index,
+            ),
+        }
+    }
+
+    report_failures("cropped", &failed, all_files.len());
+
+    index.save().unwrap_or_else(|e| {
+        eprintln!("Unable to save wallpaper index: {e}");
+    });
+
+    // open in wallfacer
+    to_process.numeric_sort();
+    let images = to_process
+        .into_iter()
+        .map(|path| path.display().to_string())
+        .collect_vec();
+
+    // process the images in wallfacer
+    run_wallfacer(images);
+}
+
+/// finds the index of the resolution by name or by <width>x<height>
+fn find_resolution(cfg: &Config, name: &str) -> usize {
+    let dimensions = parse_dimensions(name).ok();
+
+    cfg.resolutions
+        .iter()
+        .position(|res| res.name == name || dimensions == Some((res.width, res.height)))
+        .unwrap_or_else(|| {
+            eprintln!(
+                "Unknown resolution {name}, valid resolutions are: {}",
+                cfg.resolutions.iter().map(|res| &res.name).join(", ")
+            );
+            std::process::exit(1);
+        })
+}
+
+fn save_config(cfg: &Config, config_path: Option<PathBuf>) {
+    cfg.save(config_path).unwrap_or_else(|_| {
+        eprintln!("Unable to save the config, please update it manually.");
+        std::process::exit(1);
+    });
+}
+
+fn remove(mut cfg: Config, config_path: Option<PathBuf>, args: &RemoveResolutionArgs) {
+    let idx = find_resolution(&cfg, &args.name);
+
+    // the gui and the pipeline need at least one resolution to crop for
+    if cfg.resolutions.len() == 1 {
+        eprintln!("Unable to remove {}, it is the only resolution", args.name);
+        std::process::exit(1);
+    }
+
+    let removed = cfg.resolutions.remove(idx);
+    save_config(&cfg, config_path);
+    println!(
+        "Removed {} ({}x{})",
+        removed.name, removed.width, removed.height
+    );
+
+    let Some(output) = args.output.as_ref().filter(|_| args.strip) else {
+        return;
+    };
+
+    // other monitors with the same aspect ratio still use the crops
+    let ratio = removed.resolution;
+    if cfg.resolutions.iter().any(|res| res.resolution == ratio) {
+        println!("Keeping the {ratio} crops as they are used by other resolutions");
+        return;
+    }
+
+    let mut index = WallIndex::load(&cfg.metadata);
+    let mut stripped = 0;
+    let mut failed = Vec::new();
+
+    let mut all_files = filter_images(output).collect_vec();
+    all_files.numeric_sort();
+
+    for path in &all_files {
+        let mut info = match index.get(path) {
+            Ok(info) => info,
+            Err(e) => {
+                failed.push((path, e));
+                continue;
+            }
+        };
+
+        let had_crop = info.geometries.shift_remove(&ratio).is_some();
+        let had_provenance = info.provenance.shift_remove(&ratio).is_some();
+        if !had_crop && !had_provenance {
+            continue;
+        }
+
+        match index.save_info(&info) {
+            Ok(()) => stripped += 1,
+            Err(e) => failed.push((path, e)),
+        }
+    }
+
+    println!(
+        "Removed the {ratio} crops from {stripped} / {} wallpapers",
+        all_files.len()
+    );
+
+    report_failures("stripped", &failed, all_files.len());
+
+    index.save().unwrap_or_else(|e| {
+        eprintln!("Unable to save wallpaper index: {e}");
+    });
+}
+
+fn rename(mut cfg: Config, config_path: Option<PathBuf>, args: &RenameResolutionArgs) {
+    if cfg.resolutions.iter().any(|res| res.name == args.new_name) {
+        eprintln!("Resolution {} already exists", args.new_name);
+        std::process::exit(1);
+    }
+
+    let idx = find_resolution(&cfg, &args.name);
+    let res = &mut cfg.resolutions[idx];
+    let old_name = std::mem::replace(&mut res.name, args.new_name.clone());
+
+    // descriptions default to the name when added
+    if res.description.as_ref() == Some(&old_name) {
+        res.description = Some(args.new_name.clone());
+    }
+
+    // the crops are stored by aspect ratio, so the wallpapers don't need to be updated
+    save_config(&cfg, config_path);
+    println!("Renamed {old_name} to {}", args.new_name);
+}
+
+fn list(cfg: &Config, args: &ListResolutionArgs) {
+    let mut index = WallIndex::load(&cfg.metadata);
+    let all_files = args
+        .output
+        .as_ref()
+        .map(|output| filter_images(output).collect_vec())
+        .unwrap_or_default();
+
+    // (custom, default) crops for each aspect ratio
+    let mut counts = vec![(0, 0); cfg.resolutions.len()];
+    let mut failed = Vec::new();
+
+    for path in &all_files {
+        let info = match index.get(path) {
+            Ok(info) => info,
+            Err(e) => {
+                failed.push((path, e));
+                continue;
+            }
+        };
+
+        for (res, (custom, default)) in cfg.resolutions.iter().zip(counts.iter_mut()) {
+            if info.is_auto_crop(&res.resolution) {
+                *default += 1;
+            } else {
+                *custom += 1;
+            }
+        }
+    }
+
+    for (res, (custom, default)) in cfg.resolutions.iter().zip(counts) {
+        let counts = if args.output.is_some() {
+            format!(": {custom} custom, {default} default")
+        } else {
+            String::new()
+        };
+
+        println!(
+            "{} ({}x{}, {}){counts}",
+            res.name, res.width, res.height, res.resolution
+        );
+    }
+
+    report_failures("read", &failed, all_files.len());
+
+    index.save().unwrap_or_else(|e| {
+        eprintln!("Unable to save wallpaper index: {e}");
+    });
+}
+
+pub fn main(config_path: Option<PathBuf>, args: &ResolutionArgs) {
+    let cfg = Config::new(config_path.clone()).expect("failed to load config");
+
+    match &args.command {
+        Some(ResolutionCommands::Add(add_args)) => add(cfg, config_path, add_args),
+        Some(ResolutionCommands::Remove(remove_args)) => remove(cfg, config_path, remove_args),
+        Some(ResolutionCommands::Rename(rename_args)) => rename(cfg, config_path, rename_args),
+        Some(ResolutionCommands::List(list_args)) => list(&cfg, list_args),
+        None => add(
+            cfg,
+            config_path,
+            args.legacy_add
+                .as_ref()
+                .expect("clap requires either a subcommand or the resolution to add"),
+        ),
+    }
+}
@@ -1,70 +1,100 @@
 #![allow(non_snake_case)]
 use clap::Parser;
 use dioxus::prelude::*;
+use std::{collections::HashMap, path::PathBuf};
 
+use dioxus::desktop::{
+    tao::event::{Event as WryEvent, WindowEvent},
+    use_wry_event_handler,
+};
 use wallfacer::{
     cli::{Commands, WallfacerArgs},
-    config::Config,
+    config::{Config, UnsavedPolicy},
+    keybindings::{Action, Keybindings, Modifiers},
 };
 
 use crate::{
-    components::{app_header::AppHeader, save_button::save_image},
+    components::{
+        app_header::AppHeader,
+        broken_banner::BrokenBanner,
+        help_overlay::HelpOverlay,
+        save_button::save_image,
+        unsaved_prompt::{UnsavedPrompt, navigate, save_changes},
+    },
     screens::{
         adding::Adding,
-        editor::{Editor, handle_arrow_keys_keyup, handle_editor_shortcuts},
+        editor::{Editor, handle_arrow_keys_keyup, handle_arrows_keydown, handle_editor_shortcuts},
         filelist::FileList,
     },
-    state::{UiMode, UiState, Wall, Wallpapers},
+    state::{Navigation, UiMode, UiState, Wall, Wallpapers},
 };
 
+/// key as it is written in the keybindings
+fn key_name(key: Key) -> String {
+    match key {
+        Key::Character(c) if c == " " => "Space".to_string(),
+        Key::Character(c) => c,
+        key => key.to_string(),
+    }
+}
+
 fn handle_shortcuts(
     evt: &Event<KeyboardData>,
+    keybindings: &Keybindings,
     wall: &mut Signal<Wall>,
     wallpapers: &mut Signal<Wallpapers>,
     ui: &mut Signal<UiState>,
 ) {
-    match evt.key() {
-        Key::Character(shortcut) => {
-            let shortcut = shortcut.as_str();
-
-            match shortcut {
-                "/" => {
-                    ui.with_mut(UiState::toggle_filelist);
-                }
+    let editing = ui().mode == UiMode::Editor;
+    let modifiers = Modifiers {
+        ctrl: evt.modifiers().ctrl(),
+        shift: evt.modifiers().shift(),
+        alt: evt.modifiers().alt(),
+        meta: evt.modifiers().meta(),
+    };
 
-                // ctrl+f
-                "f" => {
-                    if evt.modifiers().ctrl() {
-                        ui.with_mut(UiState::toggle_filelist);
-                    }
-                }
+    match keybindings.action(&key_name(evt.key()), modifiers, editing) {
+        Some(Action::ToggleFilelist) => {
+            ui.with_mut(UiState::toggle_filelist);
+        }
 
-                // ctrl+s
-                "s" => {
-                    if evt.modifiers().ctrl() && !wallpapers().files.is_empty() {
-                        save_image(&wall(), wallpapers);
-                    }
-                }
+        Some(Action::Quit) => {
+            navigate(Navigation::Close, wall, wallpapers);
+        }
 
-                _ => {
-                    if ui().mode == UiMode::Editor {
-                        handle_editor_shortcuts(evt, wall, wallpapers, ui);
-                    }
-                }
+        Some(Action::Save) => {
+            if !wallpapers().files.is_empty() {
+                save_image(&wall(), wallpapers);
             }
         }
 
-        // devtools, no-op for release builds
-        Key::F12 => {
-            let window = dioxus::desktop::use_window();
-            window.devtool();
+        Some(Action::Help) => {
+            ui.with_mut(|ui| {
+                ui.show_help = !ui.show_help;
+            });
         }
 
-        _ => {
-            if ui().mode == UiMode::Editor {
-                handle_editor_shortcuts(evt, wall, wallpapers, ui);
+        Some(action) => handle_editor_shortcuts(action, wall, wallpapers, ui),
+
+        None => match evt.key() {
+            Key::Escape => {
+                ui.with_mut(|ui| {
+                    ui.show_help = false;
+                });
             }
-        }
+
+            // devtools, no-op for release builds
+            Key::F12 => {
+                let window = dioxus::desktop::use_window();
+                window.devtool();
+            }
+
+            key => {
+                if editing {
+                    handle_arrows_keydown(&key, wall, ui);
+                }
+            }
+        },
     }
 }
 
@@ -100,33 +130,95 @@ pub fn App() -> Element {
     }
 }
 
+/// the current wallpaper, unreadable wallpapers are marked as broken and skipped
+fn readable_wall(wallpapers: &mut Signal<Wallpapers>) -> Option<Wall> {
+    while !wallpapers.peek().files.is_empty() {
+        let current = wallpapers.peek().current();
+        match current {
+            Ok(wall) => return Some(wall),
+            Err(e) => wallpapers.with_mut(|wallpapers| wallpapers.skip_broken(&e)),
+        }
+    }
+    None
+}
+
 #[component]
-fn Main(config: Signal<Config>, wallpapers: Signal<Wallpapers>) -> Element {
-    let mut wall = use_signal(|| wallpapers().current());
+fn Main(config: Signal<Config>, mut wallpapers: Signal<Wallpapers>) -> Element {
+    let mut wall = use_signal(|| {
+        readable_wall(&mut wallpapers).unwrap_or_else(|| {
+            eprintln!("Unable to read any of the wallpapers");
+            std::process::exit(1);
+        })
+    });
     let mut ui = use_context_provider(|| {
         Signal::new(UiState {
             show_faces: config().show_faces,
+            show_broken: !wallpapers().broken.is_empty(),
             ..UiState::default()
         })
     });
 
+    // unsaved edits and their history, kept while navigating between wallpapers
+    let mut edits = use_signal(HashMap::<PathBuf, Wall>::new);
+
     use_effect(move || {
-        let mut new_wall = wallpapers().current();
-        let prev_ratio = wall.peek().ratio.clone();
+        // skipping a broken wallpaper updates the wallpapers, which reruns the effect
+        let broken = wallpapers().broken.len();
+        let Some(mut new_wall) = readable_wall(&mut wallpapers) else {
+            return;
+        };
+        if wallpapers.peek().broken.len() > broken {
+            ui.with_mut(|ui| ui.show_broken = true);
+        }
+        let prev_wall = wall.peek().clone();
+        let prev_ratio = prev_wall.ratio.clone();
+        let prev_span = prev_wall.span.clone();
 
-        let mut new_ratios = new_wall.ratios.iter().map(|r| r.resolution.clone());
+        let prev_path = &prev_wall.current.path;
+        let new_path = new_wall.current.path.clone();
+        if *prev_path != new_path {
+            // saved wallpapers are removed from the list and no longer need their edits
+            if prev_wall.has_changes() && wallpapers.peek().files.contains(prev_path) {
+                edits.write().insert(prev_path.clone(), prev_wall);
+            } else {
+                edits.write().remove(prev_path);
+            }
 
-        // prev ratio doesn't exist, just use first ratio returned by current()
-        if !new_ratios.any(|r| r == prev_ratio) {
-            wall.set(new_wall.clone());
-            return;
+            if let Some(mut restored) = edits.write().remove(&new_path) {
+                restored.mouseover_geom = None;
+                new_wall = restored;
+            }
+        }
+
+        // use the same ratio as before if it exists, otherwise the first ratio from current()
+        if new_wall.ratios.iter().any(|r| r.resolution == prev_ratio) {
+            new_wall.ratio = prev_ratio;
+        }
+
+        // keep editing the same span layout
+        if let Some(layout) = prev_span
+            && new_wall.layouts.contains(&layout)
+        {
+            new_wall.span = Some(layout);
         }
 
-        // use the same ratio as before
-        new_wall.ratio = prev_ratio;
         wall.set(new_wall);
     });
 
+    // closing the window from the window manager can't wait for the prompt, so prompting saves
+    // the changes rather than losing them
+    use_wry_event_handler(move |event, _| {
+        if let WryEvent::WindowEvent {
+            event: WindowEvent::CloseRequested,
+            ..
+        } = event
+            && wall.peek().is_dirty()
+            && config.peek().unsaved_changes != UnsavedPolicy::Discard
+        {
+            save_changes(&mut wall, &wallpapers);
+        }
+    });
+
     rsx! {
         main {
             class: "dark h-screen bg-ctp-base grid",
@@ -134,7 +226,13 @@ fn Main(config: Signal<Config>, wallpapers: Signal<Wallpapers>) -> Element {
             tabindex: 0,
             autofocus: true,
             onkeydown: move |evt| {
-                handle_shortcuts(&evt, &mut wall, &mut wallpapers, &mut ui);
+                handle_shortcuts(
+                    &evt,
+                    &config().keybindings,
+                    &mut wall,
+                    &mut wallpapers,
+                    &mut ui,
+                );
             },
             onkeyup: move |evt| {
                 handle_arrow_keys_keyup(&evt.key(), &mut ui);
@@ -143,12 +241,22 @@ fn Main(config: Signal<Config>, wallpapers: Signal<Wallpapers>) -> Element {
             AppHeader { wall, wallpapers }
 
             if ui().mode == UiMode::FileList {
-                FileList { wallpapers }
+                FileList { wall, wallpapers }
             } else if ui().mode == UiMode::Editor {
                 Editor { wall }
             } else if let UiMode::Adding(images) = ui().mode {
                 Adding { images }
             }
+
+            if ui().show_broken {
+                BrokenBanner { broken: wallpapers().broken }
+            }
+
+            if ui().show_help {
+                HelpOverlay { keybindings: config().keybindings }
+            }
+
+            UnsavedPrompt { wall, wallpapers }
         }
     }
 }
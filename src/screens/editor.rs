@@ -2,14 +2,15 @@
 use dioxus::prelude::*;
 use itertools::Itertools;
 use std::time::Instant;
-use wallfacer::geometry::Geometry;
+use wallfacer::{cropper::Direction, geometry::Geometry, keybindings::Action};
 
 use crate::{
     components::{
         align_buttons::AlignButtons,
         app_header::{next_image, prev_image},
-        preview::Previewer,
+        preview::{Previewer, ZOOM_STEP},
         ratio_buttons::{RatioButtons, change_ratio},
+        strategy_buttons::StrategyButtons,
     },
     state::{UiState, Wall, Wallpapers},
 };
@@ -28,109 +29,132 @@ pub fn handle_arrows_keydown(arrow_key: &Key, wall: &mut Signal<Wall>, ui: &mut
     // minimum move distance is 1px
     let delta = (velocity * start_time_ms as f64 / 1000.0).max(1.0);
 
-    match arrow_key {
-        Key::ArrowLeft | Key::ArrowUp => {
-            if start_time_ms == 0 {
-                ui.with_mut(|ui| {
-                    ui.arrow_key_start = Some(Instant::now());
-                });
-            }
-
-            let new_geom = wall().move_geometry_by(-delta);
-            wall.with_mut(|wall| {
-                wall.set_current_geometry(&new_geom);
-            });
-        }
-
-        Key::ArrowRight | Key::ArrowDown => {
-            if start_time_ms == 0 {
-                ui.with_mut(|ui| {
-                    ui.arrow_key_start = Some(Instant::now());
-                });
-            }
+    let sign = match arrow_key {
+        Key::ArrowLeft | Key::ArrowUp => -1.0,
+        Key::ArrowRight | Key::ArrowDown => 1.0,
+        _ => return,
+    };
 
-            let new_geom = wall().move_geometry_by(delta);
-            wall.with_mut(|wall| {
-                wall.set_current_geometry(&new_geom);
-            });
-        }
-
-        _ => {}
+    // crops that can only move along a single axis can be moved with any arrow key
+    let geom = wall().get_current_geometry();
+    let (dx, dy) = match (wall().current.direction(&geom), arrow_key) {
+        (Some(Direction::X), _) | (None, Key::ArrowLeft | Key::ArrowRight) => (sign * delta, 0.0),
+        _ => (0.0, sign * delta),
+    };
+
+    if start_time_ms == 0 {
+        ui.with_mut(|ui| {
+            ui.arrow_key_start = Some(Instant::now());
+        });
+        // holding down the key is undone as a single move
+        wall.with_mut(Wall::checkpoint);
     }
+
+    let new_geom = wall().move_geometry_by(dx, dy);
+    wall.with_mut(|wall| {
+        wall.update_current_geometry(&new_geom);
+    });
 }
 
 pub fn handle_editor_shortcuts(
-    evt: &Event<KeyboardData>,
+    action: Action,
     wall: &mut Signal<Wall>,
     wallpapers: &mut Signal<Wallpapers>,
     ui: &mut Signal<UiState>,
 ) {
-    let Wall {
-        current,
-        source,
-        ratio,
-        ..
-    } = wall();
+    let Wall { current, ratio, .. } = wall();
     let geom = wall().get_current_geometry();
     let mut set_geom = |geom: Geometry| wall.with_mut(|wall| wall.set_current_geometry(&geom));
 
-    match evt.key() {
-        Key::Character(shortcut) => {
-            let shortcut = shortcut.as_str();
-
-            match shortcut {
-                "f" => {
-                    ui.with_mut(|ui| {
-                        ui.show_faces = !ui.show_faces;
-                    });
-                }
-
-                "h" => {
-                    prev_image(wallpapers);
-                }
-
-                "l" => {
-                    next_image(wallpapers);
-                }
-
-                "0" => {
-                    set_geom(geom.align_start(current.width, current.height));
-                }
-
-                "m" => {
-                    set_geom(geom.align_center(current.width, current.height));
-                }
-
-                "$" => {
-                    set_geom(geom.align_end(current.width, current.height));
-                }
-
-                "u" => {
-                    set_geom(source.get_geometry(&ratio));
-                }
-
-                "d" => {
-                    set_geom(current.cropper().crop(&ratio));
-                }
-
-                // tab through ratios
-                "t" => {
-                    let ratios = wall()
-                        .ratios
-                        .into_iter()
-                        .map(|r| r.resolution)
-                        .collect_vec();
-
-                    if let Some(pos) = ratios.iter().position(|r| *r == ratio) {
-                        let next = (pos + 1) % ratios.len();
-                        change_ratio(wall, &ratios[next]);
-                    }
-                }
-                _ => {}
+    match action {
+        Action::ToggleFaces => {
+            ui.with_mut(|ui| {
+                ui.show_faces = !ui.show_faces;
+            });
+        }
+
+        Action::EditFaces => {
+            ui.with_mut(|ui| {
+                ui.edit_faces = !ui.edit_faces;
+            });
+        }
+
+        Action::CompareStrategies => {
+            ui.with_mut(|ui| {
+                ui.compare_strategies = !ui.compare_strategies;
+            });
+        }
+
+        Action::PrevImage => {
+            prev_image(wall, wallpapers);
+        }
+
+        Action::NextImage => {
+            next_image(wall, wallpapers);
+        }
+
+        Action::AlignStart => {
+            set_geom(geom.align_start(current.width, current.height));
+        }
+
+        Action::AlignCenter => {
+            set_geom(geom.align_center(current.width, current.height));
+        }
+
+        Action::AlignEnd => {
+            set_geom(geom.align_end(current.width, current.height));
+        }
+
+        Action::SourceCrop => {
+            set_geom(wall().get_source_geometry());
+        }
+
+        Action::DefaultCrop => {
+            set_geom(wall().get_default_geometry());
+        }
+
+        Action::Propagate => {
+            wall.with_mut(Wall::propagate_current_geometry);
+        }
+
+        // zoom about the center of the crop
+        Action::ZoomIn | Action::ZoomOut => {
+            let scale = if action == Action::ZoomOut {
+                ZOOM_STEP
+            } else {
+                1.0 / ZOOM_STEP
+            };
+            let center = (
+                f64::from(geom.x) + f64::from(geom.w) / 2.0,
+                f64::from(geom.y) + f64::from(geom.h) / 2.0,
+            );
+            wall.with_mut(|wall| wall.set_current_geometry(&wall.zoom_geometry_by(scale, center)));
+        }
+
+        Action::Undo => {
+            wall.with_mut(Wall::undo);
+        }
+
+        Action::Redo => {
+            wall.with_mut(Wall::redo);
+        }
+
+        // tab through ratios
+        Action::NextRatio => {
+            let ratios = wall()
+                .ratios
+                .into_iter()
+                .map(|r| r.resolution)
+                .collect_vec();
+
+            if let Some(pos) = ratios.iter().position(|r| *r == ratio) {
+                let next = (pos + 1) % ratios.len();
+                change_ratio(wall, &ratios[next]);
             }
         }
 
-        key => handle_arrows_keydown(&key, wall, ui),
+        // handled by the app
+        Action::ToggleFilelist | Action::Quit | Action::Save | Action::Help => {}
     }
 }
 
@@ -139,10 +163,12 @@ pub fn Editor(wall: Signal<Wall>) -> Element {
     rsx! {
         div {
             class: "grid grid-flow-col gap-4 items-center p-4 box-border",
-            style: "grid-template-columns: 1fr auto auto;",
+            style: "grid-template-columns: 1fr auto auto auto;",
 
             RatioButtons { wall },
 
+            StrategyButtons { wall },
+
             AlignButtons { wall },
         }
 
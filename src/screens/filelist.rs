@@ -1,7 +1,10 @@
 #![allow(non_snake_case)]
 use dioxus::prelude::*;
 
-use crate::{components::use_ui, state::Wallpapers};
+use crate::{
+    components::unsaved_prompt::navigate,
+    state::{Navigation, Wall, Wallpapers},
+};
 use wallfacer::filename;
 
 #[component]
@@ -42,9 +45,11 @@ fn WallpaperFile(filename: String, bytes: u64, onclick: EventHandler<MouseEvent>
 }
 
 #[component]
-pub fn FileList(wallpapers: Signal<Wallpapers>, class: Option<String>) -> Element {
-    let mut ui = use_ui();
-
+pub fn FileList(
+    wall: Signal<Wall>,
+    wallpapers: Signal<Wallpapers>,
+    class: Option<String>,
+) -> Element {
     let mut search = use_signal(String::new);
     let normalized = search().to_lowercase();
 
@@ -100,12 +105,7 @@ pub fn FileList(wallpapers: Signal<Wallpapers>, class: Option<String>) -> Elemen
                         filename: fname.clone(),
                         bytes,
                         onclick: move |_| {
-                            wallpapers.with_mut(|wallpapers| {
-                                wallpapers.set_from_filename(&fname);
-                            });
-                            ui.with_mut(|ui| {
-                                ui.toggle_filelist();
-                            });
+                            navigate(Navigation::File(fname.clone()), &mut wall, &mut wallpapers);
                         },
                     }
                 }
@@ -15,12 +15,31 @@ pub enum UiMode {
     Adding(Vec<PathBuf>),
 }
 
+/// moving away from the current wallpaper, which may need to wait for unsaved changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Navigation {
+    Prev,
+    Next,
+    /// jump to a wallpaper from the file list
+    File(String),
+    Close,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct UiState {
     pub mode: UiMode,
     pub show_faces: bool,
+    /// draw, move and resize faces instead of moving the crop
+    pub edit_faces: bool,
+    /// show the crop of every strategy so they can be compared
+    pub compare_strategies: bool,
     pub is_saving: bool,
     pub is_applying_wallpaper: bool,
+    pub show_broken: bool,
+    /// overlay listing the keybindings
+    pub show_help: bool,
+    /// navigation waiting for the user to decide what to do with unsaved changes
+    pub pending_navigation: Option<Navigation>,
     pub arrow_key_start: Option<std::time::Instant>,
 }
 
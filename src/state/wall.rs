@@ -2,10 +2,20 @@ use itertools::Itertools;
 use std::path::PathBuf;
 
 use wallfacer::{
-    aspect_ratio::AspectRatio, config::ConfigResolution, cropper::Direction, geometry::Geometry,
+    aspect_ratio::AspectRatio,
+    config::ConfigResolution,
+    crop_strategy::{CropConfig, CropStrategyKind},
+    crop_transfer::transfer_crop,
+    cropper::{Cropper, Direction},
+    energy::LazyEnergy,
+    geometry::Geometry,
+    layout::SpanLayout,
     wallpapers::WallInfo,
 };
 
+/// smallest crop allowed when zooming, relative to the largest crop
+const MAX_ZOOM: f64 = 4.0;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Wall {
     /// the original wallinfo before any modifications
@@ -16,12 +26,30 @@ pub struct Wall {
     path: PathBuf,
     /// possible ratios for this image
     pub ratios: Vec<ConfigResolution>,
+    /// multi-monitor layouts, the crops spanning them are edited separately from the ratios
+    pub layouts: Vec<SpanLayout>,
+    /// currently selected layout, takes precedence over the ratio
+    pub span: Option<SpanLayout>,
+    /// strategy used for the default crops
+    pub crop: CropConfig,
+    /// detail of the image, only loaded when a crop without faces is needed
+    energy: LazyEnergy,
     pub mouseover_geom: Option<Geometry>,
+    /// previous states of current, most recent last
+    undo_stack: Vec<WallInfo>,
+    redo_stack: Vec<WallInfo>,
 }
 
 impl Wall {
-    pub fn new(info: &WallInfo, path: PathBuf, resolutions: &[ConfigResolution]) -> Self {
-        let ratios = resolutions
+    pub fn new(
+        info: &WallInfo,
+        path: PathBuf,
+        resolutions: &[ConfigResolution],
+        layouts: &[SpanLayout],
+        crop: &CropConfig,
+        energy: LazyEnergy,
+    ) -> Self {
+        let mut ratios = resolutions
             .iter()
             .filter(|res| {
                 const THRESHOLD: f64 = 1.0 / 100.0;
@@ -49,6 +77,12 @@ impl Wall {
             .cloned()
             .collect_vec();
 
+        // every resolution matches the image, e.g. a portrait image with only a portrait monitor,
+        // show them anyway so the image can still be previewed
+        if ratios.is_empty() {
+            ratios = resolutions.to_vec();
+        }
+
         Self {
             source: info.clone(),
             current: info.clone(),
@@ -59,7 +93,13 @@ impl Wall {
                 .resolution
                 .clone(),
             ratios,
+            layouts: layouts.to_vec(),
+            span: None,
+            crop: crop.clone(),
+            energy,
             mouseover_geom: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
         }
     }
 
@@ -70,54 +110,256 @@ impl Wall {
             .unwrap_or_else(|| panic!("could not convert {} to str", self.path.display()))
     }
 
+    /// aspect ratio of the crop being edited, which is the entire layout for spans
+    pub fn current_ratio(&self) -> AspectRatio {
+        self.span
+            .as_ref()
+            .map_or_else(|| self.ratio.clone(), SpanLayout::aspect_ratio)
+    }
+
     /// gets geometry for current aspect ratio
     pub fn get_current_geometry(&self) -> Geometry {
-        self.current.get_geometry(&self.ratio)
+        match &self.span {
+            Some(layout) => self.current.get_span(layout, &self.cropper()),
+            None => self.current.get_geometry(&self.ratio, &self.cropper()),
+        }
+    }
+
+    /// gets the saved geometry for the current aspect ratio
+    pub fn get_source_geometry(&self) -> Geometry {
+        match &self.span {
+            Some(layout) => self.source.get_span(layout, &self.source_cropper()),
+            None => self
+                .source
+                .get_geometry(&self.ratio, &self.source_cropper()),
+        }
+    }
+
+    /// cropper for the current wallpaper using the configured strategy
+    pub fn cropper(&self) -> Cropper {
+        self.current
+            .cropper()
+            .with_strategy(self.crop.strategy)
+            .with_energy(self.energy.clone())
+    }
+
+    /// cropper for the saved wallpaper, whose faces can differ from the current ones
+    pub fn source_cropper(&self) -> Cropper {
+        self.source
+            .cropper()
+            .with_strategy(self.crop.strategy)
+            .with_energy(self.energy.clone())
+    }
+
+    /// gets the default geometry for the current aspect ratio
+    pub fn get_default_geometry(&self) -> Geometry {
+        self.cropper().crop(&self.current_ratio())
+    }
+
+    /// gets the geometry another strategy would use for the current aspect ratio
+    pub fn get_strategy_geometry(&self, strategy: CropStrategyKind) -> Geometry {
+        self.cropper()
+            .with_strategy(strategy)
+            .crop(&self.current_ratio())
     }
 
-    /// sets the geometry for current aspect ratio
+    /// sets the geometry for current aspect ratio, the change can be undone
     pub fn set_current_geometry(&mut self, geom: &Geometry) {
-        self.current.set_geometry(&self.ratio, geom);
+        if *geom == self.get_current_geometry() {
+            return;
+        }
+
+        self.checkpoint();
+        self.update_current_geometry(geom);
+    }
+
+    /// sets the geometry for current aspect ratio without recording it in the history, used for
+    /// continuous changes such as dragging, which should be preceded by a checkpoint
+    pub fn update_current_geometry(&mut self, geom: &Geometry) {
+        match &self.span {
+            Some(layout) => self.current.set_span(layout, geom),
+            None => self.current.set_geometry(&self.ratio, geom),
+        }
     }
 
-    /// moves the crop area of the current wallpaper based on its direction
-    pub fn move_geometry_by(&self, delta: f64) -> Geometry {
+    /// derives the crops of every other ratio from the current crop, the change can be undone
+    ///
+    /// with a span selected, the span crop is transferred to every ratio so the crops of the
+    /// individual monitors follow how the wallpaper was framed across all of them
+    pub fn propagate_current_geometry(&mut self) {
+        let geom = self.get_current_geometry();
+        let cropper = self.cropper();
+
+        self.checkpoint();
+        for res in &self.ratios {
+            if self.span.is_none() && res.resolution == self.ratio {
+                continue;
+            }
+
+            let new_geom = transfer_crop(&cropper, &geom, &res.resolution);
+            self.current.set_geometry(&res.resolution, &new_geom);
+        }
+    }
+
+    /// records the current state so it can be restored with undo
+    pub fn checkpoint(&mut self) {
+        if self.undo_stack.last() != Some(&self.current) {
+            self.undo_stack.push(self.current.clone());
+            self.redo_stack.clear();
+        }
+    }
+
+    pub fn undo(&mut self) {
+        // skip checkpoints where nothing was changed afterwards
+        while let Some(prev) = self.undo_stack.pop() {
+            if prev != self.current {
+                self.redo_stack
+                    .push(std::mem::replace(&mut self.current, prev));
+                return;
+            }
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(next) = self.redo_stack.pop() {
+            self.undo_stack
+                .push(std::mem::replace(&mut self.current, next));
+        }
+    }
+
+    /// replaces the faces, the change can be undone
+    pub fn set_faces(&mut self, faces: Vec<Geometry>) {
+        self.checkpoint();
+        self.update_faces(faces);
+    }
+
+    /// replaces the faces without recording it in the history, crops that were using the
+    /// default crop are recomputed with the new faces
+    pub fn update_faces(&mut self, faces: Vec<Geometry>) {
+        let cropper = self.cropper();
+        self.current.update_faces(faces, &cropper, &self.layouts);
+    }
+
+    /// the crops differ from the saved wallpaper
+    pub fn is_dirty(&self) -> bool {
+        self.current != self.source
+    }
+
+    /// reverts to the saved wallpaper, the discarded changes can still be restored with undo
+    pub fn discard_changes(&mut self) {
+        self.checkpoint();
+        self.current = self.source.clone();
+    }
+
+    /// the current wallpaper to be saved, with the provenance of the changed crops
+    pub fn saved_info(&self) -> WallInfo {
+        let mut info = self.current.clone();
+        info.update_provenance(&self.source, &self.cropper());
+        info
+    }
+
+    /// marks the current crops as saved
+    pub fn mark_saved(&mut self) {
+        self.source = self.current.clone();
+    }
+
+    /// the wallpaper has unsaved changes or history that should be kept while navigating
+    pub fn has_changes(&self) -> bool {
+        self.is_dirty() || !self.undo_stack.is_empty() || !self.redo_stack.is_empty()
+    }
+
+    /// moves the crop area of the current wallpaper, keeping it within the image
+    pub fn move_geometry_by(&self, dx: f64, dy: f64) -> Geometry {
         let current_geom = self.get_current_geometry();
+        let max_x = f64::from(self.current.width - current_geom.w);
+        let max_y = f64::from(self.current.height - current_geom.h);
 
-        let negative_delta = delta.is_sign_negative();
-        let delta = (if negative_delta { -delta } else { delta }) as u32;
-
-        match self.current.direction(&current_geom) {
-            Direction::X => Geometry {
-                x: if negative_delta {
-                    current_geom.x.max(delta) - delta
-                } else {
-                    (current_geom.x + delta).min(self.current.width - current_geom.w)
-                },
-                ..current_geom
-            },
-            Direction::Y => Geometry {
-                y: if negative_delta {
-                    current_geom.y.max(delta) - delta
-                } else {
-                    (current_geom.y + delta).min(self.current.height - current_geom.h)
-                },
-                ..current_geom
-            },
+        Geometry {
+            x: (f64::from(current_geom.x) + dx).clamp(0.0, max_x) as u32,
+            y: (f64::from(current_geom.y) + dy).clamp(0.0, max_y) as u32,
+            ..current_geom
+        }
+    }
+
+    /// size of the crop with the given height, the width is derived from the largest crop so the
+    /// aspect ratio stays the same as the default crops
+    fn crop_size(&self, h: f64) -> (u32, u32) {
+        let (max_w, max_h, _) = self.current.cropper().crop_rect(&self.current_ratio());
+        let min_h = f64::from(max_h) / MAX_ZOOM;
+
+        let h = h.clamp(min_h, f64::from(max_h));
+        let w = h * f64::from(max_w) / f64::from(max_h);
+
+        (w.round() as u32, h.round() as u32)
+    }
+
+    /// scales the crop area of the current wallpaper about a point in image coordinates
+    pub fn zoom_geometry_by(&self, scale: f64, (px, py): (f64, f64)) -> Geometry {
+        let current_geom = self.get_current_geometry();
+        let (w, h) = self.crop_size(f64::from(current_geom.h) * scale);
+
+        // the point stays at the same relative position within the crop
+        let rel_x = (px - f64::from(current_geom.x)) / f64::from(current_geom.w);
+        let rel_y = (py - f64::from(current_geom.y)) / f64::from(current_geom.h);
+
+        Geometry {
+            w,
+            h,
+            x: rel_x
+                .mul_add(-f64::from(w), px)
+                .clamp(0.0, f64::from(self.current.width - w)) as u32,
+            y: rel_y
+                .mul_add(-f64::from(h), py)
+                .clamp(0.0, f64::from(self.current.height - h)) as u32,
+        }
+    }
+
+    /// resizes the crop area of the current wallpaper from an anchored corner towards a point in
+    /// image coordinates, keeping the aspect ratio
+    pub fn resize_geometry_to(&self, (ax, ay): (f64, f64), (px, py): (f64, f64)) -> Geometry {
+        let (img_w, img_h) = self.current.dimensions_f64();
+        let (max_w, max_h, _) = self.current.cropper().crop_rect(&self.current_ratio());
+        let ratio = f64::from(max_w) / f64::from(max_h);
+
+        // space available in the direction of the point
+        let avail_w = if px >= ax { img_w - ax } else { ax };
+        let avail_h = if py >= ay { img_h - ay } else { ay };
+
+        let h = ((px - ax).abs() / ratio)
+            .max((py - ay).abs())
+            .min(avail_h)
+            .min(avail_w / ratio);
+        let (w, h) = self.crop_size(h);
+
+        let x = if px >= ax { ax } else { ax - f64::from(w) };
+        let y = if py >= ay { ay } else { ay - f64::from(h) };
+
+        Geometry {
+            w,
+            h,
+            x: x.clamp(0.0, f64::from(self.current.width - w)) as u32,
+            y: y.clamp(0.0, f64::from(self.current.height - h)) as u32,
         }
     }
 
     /// centers the geometry on a face
     pub fn center_on_face(&self, face: &Geometry) -> Geometry {
         let geom = self.get_current_geometry();
-        let direction = if self.current.height == geom.h {
-            Direction::X
-        } else {
-            Direction::Y
-        };
 
-        self.current
-            .cropper()
-            .crop_single_face(face, direction, geom.w, geom.h)
+        match self.current.direction(&geom) {
+            Some(direction) => self
+                .current
+                .cropper()
+                .crop_single_face(face, direction, geom.w, geom.h),
+            // zoomed crops are centered on the face along both axes
+            None => {
+                let dx = f64::from(face.x + face.xmax()) / 2.0
+                    - (f64::from(geom.x) + f64::from(geom.w) / 2.0);
+                let dy = f64::from(face.y + face.ymax()) / 2.0
+                    - (f64::from(geom.y) + f64::from(geom.h) / 2.0);
+
+                self.move_geometry_by(dx, dy)
+            }
+        }
     }
 }
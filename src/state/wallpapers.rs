@@ -1,23 +1,57 @@
 use itertools::Itertools;
-use std::path::PathBuf;
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc};
 
 use wallfacer::{
     PathBufVecExt,
     aspect_ratio::AspectRatio,
     cli::{FacesFilter, GuiArgs},
     config::{Config, ConfigResolution},
+    crop_strategy::CropConfig,
+    energy::LazyEnergy,
     filename,
-    wallpapers::WallInfo,
+    index::WallIndex,
+    layout::SpanLayout,
+    wallpapers::{WallInfo, WallInfoError},
 };
 
 use super::Wall;
 
+/// index shared between all copies of the wallpapers, as signals are cloned on every read
+#[derive(Debug, Clone)]
+pub struct SharedIndex(Rc<RefCell<WallIndex>>);
+
+impl PartialEq for SharedIndex {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedIndex {}
+
+/// energy of the wallpapers that have been shown, so it isn't recomputed when navigating back
+#[derive(Debug, Clone, Default)]
+pub struct SharedEnergy(Rc<RefCell<HashMap<PathBuf, LazyEnergy>>>);
+
+impl PartialEq for SharedEnergy {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedEnergy {}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Wallpapers {
     pub files: Vec<PathBuf>,
     pub index: usize,
     pub ratio: AspectRatio,
     pub resolutions: Vec<ConfigResolution>,
+    pub layouts: Vec<SpanLayout>,
+    pub crop: CropConfig,
+    /// wallpapers that were skipped due to unreadable metadata
+    pub broken: Vec<String>,
+    wall_index: SharedIndex,
+    energy: SharedEnergy,
 }
 
 impl Wallpapers {
@@ -58,10 +92,11 @@ impl Wallpapers {
         let unmodified_filters = Self::resolution_arg(args.unmodified.as_deref(), &resolutions);
 
         let mut all_files = args.paths.filter_wallpapers();
+        let mut broken = Vec::new();
+        let mut index = WallIndex::load(&cfg.metadata);
 
         // filter only wallpapers that still use the default crops if needed
         all_files.retain(|f| {
-            let info = WallInfo::new_from_file(f);
             if args.filter.is_some()
                 && !filename(f).to_lowercase().contains(
                     &args
@@ -74,6 +109,15 @@ impl Wallpapers {
                 return false;
             }
 
+            let info = match index.get(f) {
+                Ok(info) => info,
+                Err(e) => {
+                    eprintln!("Skipping {}: {e}", f.display());
+                    broken.push(format!("{}: {e}", filename(f)));
+                    return false;
+                }
+            };
+
             // check if wallpaper uses default crop for a resolution / all resolutions
             if !modified_filters.is_empty() {
                 return info.is_default_crops(&modified_filters);
@@ -100,18 +144,59 @@ impl Wallpapers {
         });
         all_files.reverse();
 
+        if let Err(e) = index.save() {
+            eprintln!("Unable to save wallpaper index: {e}");
+        }
+
         Self {
             index: Default::default(),
             files: all_files,
             ratio: resolutions[0].clone(),
             resolutions: cfg.resolutions.clone(),
+            layouts: cfg.layouts.clone(),
+            crop: cfg.crop.clone(),
+            broken,
+            wall_index: SharedIndex(Rc::new(RefCell::new(index))),
+            energy: SharedEnergy::default(),
         }
     }
 
-    pub fn current(&self) -> Wall {
+    /// reads the current wallpaper, which fails if its metadata became unreadable since starting
+    pub fn current(&self) -> Result<Wall, WallInfoError> {
         let path = self.files[self.index].clone();
-        let info = WallInfo::new_from_file(&path);
-        Wall::new(&info, path, &self.resolutions)
+        let info = self.wall_index.0.borrow_mut().get(&path)?;
+        let energy = self
+            .energy
+            .0
+            .borrow_mut()
+            .entry(path.clone())
+            .or_insert_with(|| LazyEnergy::new(&path))
+            .clone();
+
+        Ok(Wall::new(
+            &info,
+            path,
+            &self.resolutions,
+            &self.layouts,
+            &self.crop,
+            energy,
+        ))
+    }
+
+    /// marks the current wallpaper as broken and removes it from the list, like from_args does
+    pub fn skip_broken(&mut self, e: &WallInfoError) {
+        let path = &self.files[self.index];
+        eprintln!("Skipping {}: {e}", path.display());
+        self.broken.push(format!("{}: {e}", filename(path)));
+        self.remove();
+    }
+
+    /// saves the wallpaper and updates the index
+    pub fn save(&self, info: &WallInfo) -> Result<(), WallInfoError> {
+        let mut index = self.wall_index.0.borrow_mut();
+        index.save_info(info)?;
+        index.save()?;
+        Ok(())
     }
 
     pub const fn prev_wall(&mut self) {
@@ -169,6 +254,13 @@ impl Wallpapers {
             index,
             ratio: AspectRatio { w: 16, h: 9 },
             resolutions: Vec::new(),
+            layouts: Vec::new(),
+            crop: CropConfig::default(),
+            broken: Vec::new(),
+            wall_index: SharedIndex(Rc::new(RefCell::new(WallIndex::new(
+                &wallfacer::metadata::MetadataConfig::default(),
+            )))),
+            energy: SharedEnergy::default(),
         }
     }
 }
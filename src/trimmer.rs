@@ -4,7 +4,7 @@ use std::path::PathBuf;
 
 use image::{GenericImageView, ImageBuffer, ImageReader, Rgb};
 use rayon::prelude::*;
-use wallfacer::{PathBufVecExt, cli::TrimmerArgs, filename};
+use wallfacer::{PathBufVecExt, cli::TrimmerArgs, filename, workspace::TempWorkspace};
 
 fn mean(data: &[i32]) -> f64 {
     let sum = f64::from(data.iter().sum::<i32>());
@@ -34,6 +34,7 @@ fn std_deviation(data: &[i32]) -> f64 {
 struct Trimmer {
     threshold: f64,
     horizontal: bool,
+    workspace: TempWorkspace,
 }
 
 impl Trimmer {
@@ -131,15 +132,16 @@ impl Trimmer {
         }
 
         let trimmed_fname = filename(wall).replace("jpeg", "jpg").replace("jpg", "png");
-        let tmp_file = PathBuf::from("/tmp").join(&trimmed_fname);
+        let tmp_file = self
+            .workspace
+            .path_for(&trimmed_fname)
+            .expect("could not create temp file");
 
         let cropped = img.view(x, y, width, height).to_image();
         cropped
             .save(&tmp_file)
             .unwrap_or_else(|_| panic!("could not save trimmed image for {}", wall.display()));
 
-        std::thread::sleep(std::time::Duration::from_secs(1));
-
         // replace original file if it is not a jpeg
         let final_path = wall.with_file_name(&trimmed_fname);
         std::fs::copy(&tmp_file, &final_path).unwrap_or_else(|_| {
@@ -149,6 +151,9 @@ impl Trimmer {
                 final_path.display()
             )
         });
+        self.workspace
+            .remove(&tmp_file)
+            .expect("could not remove temp file");
 
         if filename(wall) != trimmed_fname {
             std::fs::remove_file(wall).unwrap_or_else(|e| {
@@ -166,6 +171,7 @@ pub fn main(args: &TrimmerArgs) {
     let trimmer = Trimmer {
         threshold: args.threshold,
         horizontal: args.horizontal,
+        workspace: TempWorkspace::new("trim").expect("could not create temp directory"),
     };
     all_files.par_iter().for_each(|wall| {
         println!("Processing: {}", wall.display());